@@ -0,0 +1,191 @@
+use std::{collections::HashMap, hash::Hash};
+
+/// The sequence of states `x_0, x_1 = step(x_0), x_2 = step(x_1), ...`, where `step` is
+/// deterministic and there are finitely many states, so that the sequence eventually
+/// cycles: there's some `μ` and `λ` such that `x_(i + λ) == x_i` for all `i >= μ`.
+pub(crate) struct Trajectory<S, F> {
+	initial: S,
+	step: F,
+	/// The index of the first state in the cycle
+	mu: usize,
+	/// The length of the cycle
+	lambda: usize,
+}
+
+impl<S, F> Trajectory<S, F>
+where
+	S: Clone,
+	F: FnMut(&S) -> S,
+{
+	/// Finds the cycle with Brent's algorithm
+	/// (<https://en.wikipedia.org/wiki/Cycle_detection#Brent's_algorithm>), which only
+	/// ever holds two states in memory, at the cost of comparing whole states.
+	///
+	/// Gives up once it reaches `x_limit` without having found the cycle, returning
+	/// `Err(x_limit)` after `limit` steps.
+	#[allow(dead_code)]
+	pub(crate) fn brent(initial: S, mut step: F, limit: usize) -> Result<Self, S>
+	where
+		S: PartialEq,
+	{
+		if limit == 0 {
+			return Err(initial);
+		}
+
+		// find λ by teleporting the tortoise to the hare at every power of two, until the
+		// hare runs into it
+		let mut power = 1;
+		let mut lambda = 1;
+		let mut tortoise = initial.clone();
+		let mut hare = step(&initial);
+		let mut hare_index = 1;
+		while tortoise != hare {
+			if hare_index == limit {
+				return Err(hare);
+			}
+			if power == lambda {
+				tortoise = hare.clone();
+				power *= 2;
+				lambda = 0;
+			}
+			hare = step(&hare);
+			hare_index += 1;
+			lambda += 1;
+		}
+
+		// find μ by starting the hare λ ahead of the tortoise; they first meet at the start
+		// of the cycle
+		let mut tortoise = initial.clone();
+		let mut hare = initial.clone();
+		for _ in 0..lambda {
+			hare = step(&hare);
+		}
+		let mut mu = 0;
+		while tortoise != hare {
+			tortoise = step(&tortoise);
+			hare = step(&hare);
+			mu += 1;
+		}
+
+		Ok(Self {
+			initial,
+			step,
+			mu,
+			lambda,
+		})
+	}
+
+	/// Finds the cycle by remembering the fingerprint of every state seen until one
+	/// repeats. `fingerprint` must be injective (distinct states must have distinct
+	/// fingerprints), but can be much smaller than the state itself.
+	///
+	/// Gives up once it reaches `x_limit` without having found the cycle, returning
+	/// `Err(x_limit)` after `limit` steps, so callers that only want `x_limit` never step
+	/// past it.
+	pub(crate) fn by_fingerprint<K: Hash + Eq>(
+		initial: S,
+		mut step: F,
+		fingerprint: impl Fn(&S) -> K,
+		limit: usize,
+	) -> Result<Self, S> {
+		let mut seen = HashMap::new();
+		let mut state = initial.clone();
+
+		for i in 0..limit {
+			if let Some(mu) = seen.insert(fingerprint(&state), i) {
+				return Ok(Self {
+					initial,
+					step,
+					mu,
+					lambda: i - mu,
+				});
+			}
+			state = step(&state);
+		}
+
+		Err(state)
+	}
+
+	#[allow(dead_code)]
+	pub(crate) fn mu(&self) -> usize {
+		self.mu
+	}
+
+	#[allow(dead_code)]
+	pub(crate) fn lambda(&self) -> usize {
+		self.lambda
+	}
+
+	/// The `n`th state, `x_n`, which takes fewer than `μ + λ` steps to compute
+	pub(crate) fn nth_state(&mut self, n: usize) -> S {
+		let Self { mu, lambda, .. } = *self;
+		let n = if n < mu { n } else { mu + (n - mu) % lambda };
+
+		let mut state = self.initial.clone();
+		for _ in 0..n {
+			state = (self.step)(&state);
+		}
+		state
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use std::cell::Cell;
+
+	type Step = fn(&u32) -> u32;
+
+	#[test]
+	fn known_cycles() {
+		// (step, μ, λ), all starting from 0
+		let cases: [(Step, usize, usize); 4] = [
+			// 0 1 2 3 4 5 2 3 4 5 ...
+			(|&x| if x < 5 { x + 1 } else { 2 }, 2, 4),
+			// 0 1 2 3 4 5 6 0 1 ...
+			(|&x| (x + 1) % 7, 0, 7),
+			// 0 1 2 3 3 3 ...
+			(|&x| (x + 1).min(3), 3, 1),
+			// x² + 1 mod 47: 0 1 2 5 26 19 33 9 35 4 17 8 18 43 17 8 ...
+			(|&x| (x * x + 1) % 47, 10, 4),
+		];
+
+		for (step, mu, lambda) in cases {
+			let sequence = std::iter::successors(Some(0), |x| Some(step(x)))
+				.take(3 * (mu + lambda))
+				.collect::<Vec<_>>();
+
+			let mut brent = Trajectory::brent(0, step, usize::MAX).unwrap();
+			let mut hashed = Trajectory::by_fingerprint(0, step, |&x| x, usize::MAX).unwrap();
+			for trajectory in [&mut brent, &mut hashed] {
+				assert_eq!((trajectory.mu(), trajectory.lambda()), (mu, lambda));
+				for (n, &x) in sequence.iter().enumerate() {
+					assert_eq!(trajectory.nth_state(n), x);
+				}
+				assert_eq!(
+					trajectory.nth_state(1_000_000_000),
+					sequence[mu + (1_000_000_000 - mu) % lambda]
+				);
+			}
+
+			// with a limit, they stop as soon as they reach `x_limit`, cycle or no cycle
+			for (limit, &x_limit) in sequence.iter().enumerate() {
+				let steps = Cell::new(0);
+				let counted = |x: &u32| {
+					steps.set(steps.get() + 1);
+					step(x)
+				};
+				if let Err(x) = Trajectory::by_fingerprint(0, counted, |&x| x, limit) {
+					assert_eq!((x, steps.get()), (x_limit, limit));
+				} else {
+					assert!(limit > mu + lambda, "{limit}");
+				}
+
+				steps.set(0);
+				if let Err(x) = Trajectory::brent(0, counted, limit) {
+					assert_eq!((x, steps.get()), (x_limit, limit));
+				}
+			}
+		}
+	}
+}
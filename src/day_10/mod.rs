@@ -1,7 +1,13 @@
 // tag::setup[]
-use crate::{read_file, Answer, AocError};
+use crate::{polygon::Polygon, read_file, Answer, AocError, AocResult, Cast};
 use ndarray::prelude::*;
-use std::{collections::HashMap, str::FromStr};
+use std::{
+	collections::{HashMap, HashSet},
+	fmt,
+	str::FromStr,
+};
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
 
 fn ans_for_input(input: &str) -> Answer<usize, usize> {
 	let input = read_input(input);
@@ -17,7 +23,7 @@ fn read_input(input: &str) -> Input {
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum Direction {
+pub enum Direction {
 	N,
 	S,
 	E,
@@ -36,8 +42,8 @@ impl Direction {
 	}
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum Pipe {
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter)]
+pub enum Pipe {
 	/// Vertical
 	Ns,
 	/// Horizontal
@@ -65,10 +71,15 @@ impl Pipe {
 			Sw => (S, W),
 		}
 	}
+
+	fn has_end(self, dir: Direction) -> bool {
+		let (d1, d2) = self.directions();
+		d1 == dir || d2 == dir
+	}
 }
 
-#[derive(Debug, Clone, Copy)]
-enum Tile {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tile {
 	Pipe(Pipe),
 	Ground,
 	Start,
@@ -93,18 +104,139 @@ impl TryFrom<char> for Tile {
 	}
 }
 
-#[derive(Debug)]
-struct Input {
+/// A pipe end that doesn't connect to a pipe pointing back at it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DanglingEnd {
+	/// The tile whose pipe has the loose end
+	pub pos: [usize; 2],
+	/// The direction the loose end points in
+	pub dir: Direction,
+	/// What the loose end points at; `None` if it points off the edge of the map
+	pub leads_to: Option<Tile>,
+}
+
+/// Follows the end of the pipe at `pos` that points in direction `dir`, returning the
+/// position of the pipe it connects to
+fn follow(
+	map: ArrayView2<Tile>,
+	pos: [usize; 2],
+	dir: Direction,
+) -> Result<[usize; 2], DanglingEnd> {
+	use Direction::*;
+
+	let [ri, ci] = pos;
+	let (nr, nc) = map.dim();
+
+	let next_pos = match dir {
+		N if ri > 0 => Some([ri - 1, ci]),
+		S if ri < nr - 1 => Some([ri + 1, ci]),
+		E if ci < nc - 1 => Some([ri, ci + 1]),
+		W if ci > 0 => Some([ri, ci - 1]),
+		_ => None,
+	};
+
+	match next_pos.map(|next_pos| (next_pos, map[next_pos])) {
+		Some((next_pos, Tile::Pipe(pipe))) if pipe.has_end(dir.invert()) => Ok(next_pos),
+		leads_to => Err(DanglingEnd {
+			pos,
+			dir,
+			leads_to: leads_to.map(|(_, tile)| tile),
+		}),
+	}
+}
+
+/// The map of pipes, with the start tile replaced by the one pipe that closes a loop
+/// through it
+#[derive(Debug, Clone)]
+pub struct Input {
 	map: Array2<Tile>,
 	start: [usize; 2],
 }
 
+/// Why a map couldn't be turned into an [`Input`]
+#[derive(Debug)]
+pub enum ParseError {
+	Invalid(AocError),
+	/// None of the pipes that fit at the start tile close a loop through it. Holds each
+	/// pipe that fit, with the dangling end that the walk from the start ran into.
+	NoClosedLoop(Vec<(Pipe, DanglingEnd)>),
+	/// More than one of the pipes that fit at the start tile close a loop through it
+	AmbiguousStart([usize; 2], Vec<Pipe>),
+}
+
+impl From<AocError> for ParseError {
+	fn from(err: AocError) -> Self {
+		Self::Invalid(err)
+	}
+}
+
+impl From<ParseError> for AocError {
+	fn from(err: ParseError) -> Self {
+		match err {
+			ParseError::Invalid(err) => err,
+			err => AocError::Other(err.to_string()),
+		}
+	}
+}
+
+impl fmt::Display for ParseError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Invalid(err) => write!(f, "{err:?}"),
+			Self::NoClosedLoop(ends) => {
+				write!(
+					f,
+					"none of the {} possible pipes at the start tile form a closed loop",
+					ends.len()
+				)?;
+				for (pipe, DanglingEnd { pos, dir, .. }) in ends {
+					write!(f, "; with {pipe:?}, the pipe at {pos:?} dangles {dir:?}")?;
+				}
+				Ok(())
+			}
+			Self::AmbiguousStart(start, pipes) => write!(
+				f,
+				"more than one pipe at start tile {start:?} forms a closed loop: {pipes:?}"
+			),
+		}
+	}
+}
+
 impl FromStr for Input {
-	type Err = AocError;
+	type Err = ParseError;
 
 	fn from_str(s: &str) -> Result<Self, Self::Err> {
-		use Direction::*;
+		let mut closed = Vec::new();
+		let mut dangling_ends = Vec::new();
+
+		for input in Self::start_candidates(s)? {
+			let Tile::Pipe(pipe) = input.map[input.start] else {
+				unreachable!("every candidate has a pipe at the start")
+			};
+			let mut walk = input.traverse();
+			walk.by_ref().for_each(drop);
+			match walk.dangling_end {
+				None => closed.push((pipe, input)),
+				Some(dangling_end) => dangling_ends.push((pipe, dangling_end)),
+			}
+		}
+
+		match closed.len() {
+			0 => Err(ParseError::NoClosedLoop(dangling_ends)),
+			1 => Ok(closed.pop().unwrap().1),
+			_ => Err(ParseError::AmbiguousStart(
+				closed[0].1.start,
+				closed.into_iter().map(|(pipe, _)| pipe).collect(),
+			)),
+		}
+	}
+}
 
+impl Input {
+	/// Parses the map, producing one `Input` for each pipe that could replace the start
+	/// tile and connect to two of its neighbors. Every candidate is returned, whether or
+	/// not its loop through the start tile actually closes.
+	fn start_candidates(s: &str) -> AocResult<Vec<Self>> {
 		let mut height = 0;
 		let mut start = None;
 
@@ -115,6 +247,12 @@ impl FromStr for Input {
 				let tile = c.try_into()?;
 				elems.push(tile);
 				if matches!(tile, Tile::Start) {
+					if let Some(prev_start) = start {
+						return Err(AocError::Other(format!(
+							"found start tiles at both {prev_start:?} and {:?}",
+							[ri, ci]
+						)));
+					}
 					start = Some([ri, ci]);
 				}
 			}
@@ -122,209 +260,337 @@ impl FromStr for Input {
 		}
 
 		let width = elems.len() / height;
-		let mut map = Array2::from_shape_vec((height, width), elems)?;
-		let start = start.unwrap();
+		let map = Array2::from_shape_vec((height, width), elems)?;
+		let start = start.ok_or_else(|| AocError::Other("no start tile".to_owned()))?;
 
-		let [start_row, start_col] = start;
+		// a pipe fits at the start if both of its ends connect to pipes pointing back at
+		// the start
+		Ok(Pipe::iter()
+			.filter_map(|pipe| {
+				let mut map = map.clone();
+				map[start] = Tile::Pipe(pipe);
 
-		let mut incoming_directions = [N; 2];
-		let mut inc_dir_idx = 0;
+				let (d1, d2) = pipe.directions();
+				(follow(map.view(), start, d1).is_ok() && follow(map.view(), start, d2).is_ok())
+					.then_some(Input { map, start })
+			})
+			.collect())
+	}
+}
 
-		let idx_err = || AocError::Other(format!("too many incoming direcions at {start:?}"));
+/// Lazily walks along connected pipes, yielding each tile (and the pipe on it) exactly
+/// once, beginning with the tile it started on. The walk ends when it either makes it
+/// back around to where it started or reaches a pipe end that doesn't connect to
+/// anything, in which case that end is recorded in `dangling_end`.
+#[derive(Debug, Clone)]
+struct LoopIter<'a> {
+	map: ArrayView2<'a, Tile>,
+	start: [usize; 2],
+	/// The next tile to yield; `None` once the walk is over
+	pos: Option<[usize; 2]>,
+	/// The direction, relative to `pos`, of the tile we came from (`None` at the start)
+	came_from: Option<Direction>,
+	dangling_end: Option<DanglingEnd>,
+}
 
-		// figure out the directions of the pipes leading into the Start pipe
-		for (ri, ci, rel_dir) in [
-			(start_row.checked_sub(1), Some(start_col), N),
-			(start_row.checked_add(1), Some(start_col), S),
-			(Some(start_row), start_col.checked_sub(1), W),
-			(Some(start_row), start_col.checked_add(1), E),
-		] {
-			if let Some(ri) = ri
-				&& let Some(ci) = ci
-				&& let Tile::Pipe(pipe) = map[[ri, ci]]
-			{
-				let (d1, d2) = pipe.directions();
-				// e.g. if the pipe north of Start has an end pointing south, then north is
-				// one of the incoming directions
-				if d1 == rel_dir.invert() || d2 == rel_dir.invert() {
-					*incoming_directions
-						.get_mut(inc_dir_idx)
-						.ok_or_else(idx_err)? = rel_dir;
-					inc_dir_idx += 1;
-				}
-			}
+impl LoopIter<'_> {
+	/// Sets off from the starting pipe through its other end. Only makes sense before the
+	/// walk has begun.
+	fn backwards(self) -> Self {
+		let Tile::Pipe(pipe) = self.map[self.start] else {
+			unreachable!("walks start on pipes")
+		};
+		let (d1, _) = pipe.directions();
+		Self {
+			came_from: Some(d1),
+			..self
 		}
+	}
+}
 
-		if inc_dir_idx < 2 {
-			return Err(AocError::Other(format!("only {inc_dir_idx} incoming pipes at {start:?}")));
-		}
+impl Iterator for LoopIter<'_> {
+	type Item = ([usize; 2], Pipe);
 
-		let starting_pipe = match incoming_directions {
-			[N, S] | [S, N] => Pipe::Ns,
-			[E, W] | [W, E] => Pipe::Ew,
-			[N, E] | [E, N] => Pipe::Ne,
-			[N, W] | [W, N] => Pipe::Nw,
-			[S, E] | [E, S] => Pipe::Se,
-			[S, W] | [W, S] => Pipe::Sw,
-			_ => {
-				return Err(
-					AocError::Other(format!("invalid incoming_directions {incoming_directions:?}"))
-				)
-			}
+	fn next(&mut self) -> Option<Self::Item> {
+		let pos = self.pos?;
+
+		let Tile::Pipe(pipe) = self.map[pos] else {
+			unreachable!("walked onto non-pipe tile {:?} at {pos:?}", self.map[pos])
 		};
 
-		map[start] = Tile::Pipe(starting_pipe);
+		// `follow` only ever leads to pipes that point back the way we came, so
+		// `came_from` is always one of the pipe's ends
+		let (d1, d2) = pipe.directions();
+		let move_dir = match self.came_from {
+			Some(prev_dir) if prev_dir == d1 => d2,
+			_ => d1,
+		};
 
-		Ok(Input { map, start })
+		match follow(self.map, pos, move_dir) {
+			Ok(next_pos) => {
+				self.pos = (next_pos != self.start).then_some(next_pos);
+				self.came_from = Some(move_dir.invert());
+			}
+			Err(dangling_end) => {
+				self.pos = None;
+				self.dangling_end = Some(dangling_end);
+			}
+		}
+
+		Some((pos, pipe))
 	}
 }
 
 impl Input {
-	// Todo: make this an iterator instead of a Vec (Rust generators when?)
-	fn traverse(&self) -> Vec<([usize; 2], Pipe)> {
-		use Direction::*;
-
-		let Input { map, start } = self;
-		let start = *start;
-		let [mut ri, mut ci] = start;
-
-		let (starting_pipe, mut move_dir) = match map[start] {
-			Tile::Pipe(pipe) => (pipe, pipe.directions().0),
-			Tile::Ground => unreachable!("attempting to start at 'ground' tile"),
-			Tile::Start => unreachable!("did not succesffully remove 'start' tile"),
-		};
+	/// Walks from the pipe at `pos`; `pos` must hold a pipe
+	fn walk_from(&self, pos: [usize; 2]) -> LoopIter<'_> {
+		LoopIter {
+			map: self.map.view(),
+			start: pos,
+			pos: Some(pos),
+			came_from: None,
+			dangling_end: None,
+		}
+	}
 
-		let mut points = vec![(start, starting_pipe)];
+	fn traverse(&self) -> LoopIter<'_> {
+		self.walk_from(self.start)
+	}
 
-		loop {
-			(ri, ci) = match move_dir {
-				N => (ri - 1, ci),
-				S => (ri + 1, ci),
-				E => (ri, ci + 1),
-				W => (ri, ci - 1),
-			};
+	/// Every closed loop on the map (not just the one through the start tile), each as
+	/// the list of tiles it passes through
+	pub fn loops(&self) -> Vec<Vec<[usize; 2]>> {
+		let mut visited = Array2::from_elem(self.map.dim(), false);
+		let mut loops = Vec::new();
+
+		// connections between pipes are mutual, so every tile connects to at most two
+		// others, and so each connected run of pipe is either a closed loop or a chain
+		// with two dangling ends
+		for (pos, tile) in self.map.indexed_iter() {
+			let pos = pos.into();
+			if visited[pos] || !matches!(tile, Tile::Pipe(_)) {
+				continue;
+			}
 
-			let prev_dir = move_dir.invert();
+			let mut walk = self.walk_from(pos);
+			let tiles = walk.by_ref().map(|(pos, _)| pos).collect::<Vec<_>>();
+			for &pos in &tiles {
+				visited[pos] = true;
+			}
 
-			let pipe;
-			(pipe, move_dir) = match map[[ri, ci]] {
-				Tile::Pipe(pipe) => {
-					let (d1, d2) = pipe.directions();
-					let dir = if prev_dir == d1 {
-						d2
-					} else if prev_dir == d2 {
-						d1
-					} else {
-						panic!(
-							"invalid move_dir, prev_dir, directions: \
-							 {move_dir:?}, {prev_dir:?}, {:?}",
-							(d1, d2)
-						);
-					};
-					(pipe, dir)
+			if walk.dangling_end.is_none() {
+				loops.push(tiles);
+			} else {
+				// a chain, of which we've only walked the part one way from `pos`; walk the
+				// other way too, so that no tile of the chain gets walked from again
+				for (pos, _) in self.walk_from(pos).backwards() {
+					visited[pos] = true;
 				}
-				Tile::Ground => unreachable!("pipes led to a ground tile"),
-				Tile::Start => {
-					unreachable!("did not succesffully remove 'start' tile, and pipes led there")
-				}
-			};
-
-			if [ri, ci] == start {
-				break;
 			}
-
-			points.push(([ri, ci], pipe));
 		}
 
-		points
+		loops
+	}
+
+	/// Every pipe end on the map that doesn't connect to a pipe pointing back at it
+	pub fn dangling_ends(&self) -> Vec<DanglingEnd> {
+		self.map
+			.indexed_iter()
+			.filter_map(|(pos, &tile)| match tile {
+				Tile::Pipe(pipe) => Some((pos.into(), pipe)),
+				_ => None,
+			})
+			.flat_map(|(pos, pipe)| {
+				let (d1, d2) = pipe.directions();
+				[d1, d2].map(|dir| follow(self.map.view(), pos, dir).err())
+			})
+			.flatten()
+			.collect()
 	}
 }
 // end::setup[]
 
 // tag::pt1[]
 fn pt1(input: &Input) -> usize {
-	input.traverse().len() / 2
+	input.traverse().count() / 2
 }
 // end::pt1[]
 
 // tag::pt2[]
-fn pt2(input: &Input) -> usize {
-	use Direction::*;
-	use Pipe::*;
-
-	// Figure out whether each non-path-pipe point on the map is "inside" or "outside" by
-	// counting its crossings with the path-pipes, starting at the point in question and
-	// heading south until the edge of the map is reached. An odd number of crossings
-	// means it's inside; an even number means it's outside.
-
-	let map = &input.map;
-	let path_points = input.traverse().into_iter().collect::<HashMap<_, _>>();
-
-	let mut n_interior_points = 0;
-
-	// This is needed to track whether, after riding along some amount of
-	// north-south-oriented pipe, when we leave that length of pipe, we've actually
-	// crossed a horizontal section of pipe, or have just ridden along e.g. the spine of
-	// an uppercase 'D'. If the beginning and end of the section of vertical pipe we're
-	// riding point in opposite directions, we crossed the path; if the same direction,
-	// we haven't. \
-	// ex: \
-	//    . \
-	//   -7 \
-	//    L- \
-	// Heading south from the dot, we do cross from inside (resp. outside) the path to
-	// outside (resp. inside). Whereas: \
-	//    . \
-	//   -7 \
-	//   -J \
-	// Heading south from the dot, we do not materially cross the path. \
-	// The initial value of this doesn't matter; it'll always be overwritten before being
-	// read.
-	let mut bend_direction = Direction::N;
-
-	for ((ri, ci), _) in map.indexed_iter() {
-		if path_points.contains_key(&[ri, ci]) {
-			continue;
-		}
+impl Input {
+	/// The number of tiles enclosed by the loop, computed from the loop's vertices alone
+	fn n_enclosed(&self) -> usize {
+		// The polygon whose vertices are the centers of the loop's corner tiles passes
+		// through the center of every tile of the loop, and the tiles it encloses are
+		// exactly the lattice points strictly inside it
+		self.polygon().n_interior_points().unwrap().cast()
+	}
+
+	/// The polygon through the centers of the loop's tiles, with `[row, col]` vertices
+	fn polygon(&self) -> Polygon {
+		use Pipe::*;
+
+		Polygon::new(
+			self.traverse()
+				.filter(|(_, pipe)| !matches!(pipe, Ns | Ew))
+				.map(|([ri, ci], _)| [ri.cast(), ci.cast()])
+				.collect(),
+		)
+	}
+
+	/// The set of tiles enclosed by the loop, found by casting a ray from every tile that
+	/// isn't part of the loop
+	fn enclosed_tiles(&self) -> HashSet<[usize; 2]> {
+		use Direction::*;
+		use Pipe::*;
+
+		// Figure out whether each non-path-pipe point on the map is "inside" or "outside" by
+		// counting its crossings with the path-pipes, starting at the point in question and
+		// heading south until the edge of the map is reached. An odd number of crossings
+		// means it's inside; an even number means it's outside.
+
+		let map = &self.map;
+		let path_points = self.traverse().collect::<HashMap<_, _>>();
+
+		let mut interior_points = HashSet::new();
+
+		// This is needed to track whether, after riding along some amount of
+		// north-south-oriented pipe, when we leave that length of pipe, we've actually
+		// crossed a horizontal section of pipe, or have just ridden along e.g. the spine of
+		// an uppercase 'D'. If the beginning and end of the section of vertical pipe we're
+		// riding point in opposite directions, we crossed the path; if the same direction,
+		// we haven't. \
+		// ex: \
+		//    . \
+		//   -7 \
+		//    L- \
+		// Heading south from the dot, we do cross from inside (resp. outside) the path to
+		// outside (resp. inside). Whereas: \
+		//    . \
+		//   -7 \
+		//   -J \
+		// Heading south from the dot, we do not materially cross the path. \
+		// The initial value of this doesn't matter; it'll always be overwritten before being
+		// read.
+		let mut bend_direction = Direction::N;
+
+		for ((ri, ci), _) in map.indexed_iter() {
+			if path_points.contains_key(&[ri, ci]) {
+				continue;
+			}
 
-		// check number of crossings between point and (perpendicular) pipes from point to
-		// exterior of map. if parity is odd, point is inside (enclosed by pipes) \
-		// note that `x ^= true` is equivalent to `x = !x`, ie `toggle` (which doesn't
-		// exist in Rust)
-		let mut odd_parity = false;
-
-		for i in ri..map.nrows() {
-			if let Some(&pipe) = path_points.get(&[i, ci]) {
-				// The N* pipes must be preceded at some point in time by a S* pipe
-				if pipe == Ew
-					|| pipe == Ne && bend_direction == W
-					|| pipe == Nw && bend_direction == E
-				{
-					odd_parity ^= true;
-				// Set the direction of the preceding bend
-				} else if pipe == Se {
-					bend_direction = E;
-				} else if pipe == Sw {
-					bend_direction = W;
+			// check number of crossings between point and (perpendicular) pipes from point to
+			// exterior of map. if parity is odd, point is inside (enclosed by pipes) \
+			// note that `x ^= true` is equivalent to `x = !x`, ie `toggle` (which doesn't
+			// exist in Rust)
+			let mut odd_parity = false;
+
+			for i in ri..map.nrows() {
+				if let Some(&pipe) = path_points.get(&[i, ci]) {
+					// The N* pipes must be preceded at some point in time by a S* pipe
+					if pipe == Ew
+						|| pipe == Ne && bend_direction == W
+						|| pipe == Nw && bend_direction == E
+					{
+						odd_parity ^= true;
+					// Set the direction of the preceding bend
+					} else if pipe == Se {
+						bend_direction = E;
+					} else if pipe == Sw {
+						bend_direction = W;
+					}
 				}
 			}
-		}
 
-		if odd_parity {
-			n_interior_points += 1;
+			if odd_parity {
+				interior_points.insert([ri, ci]);
+			}
 		}
+
+		interior_points
 	}
+}
 
-	n_interior_points
+fn pt2(input: &Input) -> usize {
+	input.n_enclosed()
 }
 // end::pt2[]
 
+impl Pipe {
+	fn box_drawing_char(self) -> char {
+		use Pipe::*;
+		match self {
+			Ns => '│',
+			Ew => '─',
+			Ne => '└',
+			Nw => '┘',
+			Se => '┌',
+			Sw => '┐',
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenderStyle {
+	/// Just the characters
+	Plain,
+	/// The same characters, colored with ANSI escape codes for display in a terminal
+	Ansi,
+}
+
+impl Input {
+	/// Draws the map in the style of the puzzle text: the main loop in box-drawing
+	/// characters, and every other tile (ground or junk pipe) as `I` if it's enclosed by
+	/// the loop or `O` if it isn't
+	pub fn render(&self, style: RenderStyle) -> String {
+		const RESET: &str = "\x1b[0m";
+		const LOOP_COLOR: &str = "\x1b[1;33m";
+		const START_COLOR: &str = "\x1b[1;31m";
+		const INSIDE_COLOR: &str = "\x1b[1;32m";
+		const OUTSIDE_COLOR: &str = "\x1b[2;34m";
+
+		let path_points = self.traverse().collect::<HashMap<_, _>>();
+		let enclosed = self.enclosed_tiles();
+
+		let mut s = String::with_capacity(self.map.len() * 4);
+		for (ri, row) in self.map.rows().into_iter().enumerate() {
+			for ci in 0..row.len() {
+				let pos = [ri, ci];
+				let (c, color) = if let Some(pipe) = path_points.get(&pos) {
+					let color = if pos == self.start {
+						START_COLOR
+					} else {
+						LOOP_COLOR
+					};
+					(pipe.box_drawing_char(), color)
+				} else if enclosed.contains(&pos) {
+					('I', INSIDE_COLOR)
+				} else {
+					('O', OUTSIDE_COLOR)
+				};
+
+				match style {
+					RenderStyle::Plain => s.push(c),
+					RenderStyle::Ansi => {
+						s.push_str(color);
+						s.push(c);
+						s.push_str(RESET);
+					}
+				}
+			}
+			s.push('\n');
+		}
+
+		s
+	}
+}
+
 #[cfg(test)]
 mod test {
 	#![allow(unused_imports)]
 
 	use super::*;
-	use crate::{run_test, run_tests};
+	use crate::{polygon::Location, regex, run_test, run_tests};
 
 	#[test]
 	fn sample() {
@@ -342,4 +608,166 @@ mod test {
 			(pt2, 445),
 		);
 	}
+
+	#[test]
+	fn enclosed_tiles() {
+		for input in [
+			read_file!("sample_input_1.txt"),
+			read_file!("sample_input_2.txt"),
+			read_file!("sample_input_3.txt"),
+			read_file!("sample_input_4.txt"),
+			read_file!("input.txt"),
+		] {
+			let input = read_input(&input);
+			let enclosed = input.enclosed_tiles();
+			assert_eq!(enclosed.len(), input.n_enclosed());
+
+			let polygon = input.polygon();
+			let on_loop = input.traverse().map(|(pos, _)| pos).collect::<HashSet<_>>();
+			for ((ri, ci), _) in input.map.indexed_iter() {
+				let expected = if on_loop.contains(&[ri, ci]) {
+					Location::Boundary
+				} else if enclosed.contains(&[ri, ci]) {
+					Location::Inside
+				} else {
+					Location::Outside
+				};
+				assert_eq!(polygon.locate([ri.cast(), ci.cast()]), expected);
+			}
+		}
+	}
+
+	#[test]
+	fn render() {
+		let input = read_input(&read_file!("sample_input_3.txt"));
+		let plain = input.render(RenderStyle::Plain);
+		assert_eq!(plain.matches('I').count(), pt2(&input));
+		assert_eq!(plain.lines().next(), Some("O┌────┐┌┐┌┐┌┐┌─┐OOOO"));
+
+		let ansi = input.render(RenderStyle::Ansi);
+		assert_eq!(regex!(r"\x1b\[[\d;]*m").replace_all(&ansi, ""), plain);
+	}
+
+	#[test]
+	fn ambiguous_start() {
+		// the start tile connects north, east, and south, but only connecting south and
+		// east closes the loop
+		let map = "\
+			.|...\n\
+			.S-7.\n\
+			.|.|.\n\
+			.L-J.\n\
+			.....\n";
+
+		let candidates = Input::start_candidates(map).unwrap();
+		assert_eq!(candidates.len(), 3);
+
+		let input = read_input(map);
+		assert!(matches!(input.map[[1, 1]], Tile::Pipe(Pipe::Se)));
+		run_test(&input, (pt1, 4));
+		run_test(&input, (pt2, 1));
+
+		assert_eq!(
+			input.dangling_ends(),
+			[
+				DanglingEnd {
+					pos: [0, 1],
+					dir: Direction::N,
+					leads_to: None,
+				},
+				DanglingEnd {
+					pos: [0, 1],
+					dir: Direction::S,
+					leads_to: Some(Tile::Pipe(Pipe::Se)),
+				},
+			]
+		);
+
+		// no pipe fits at the start at all
+		assert!(matches!(
+			"S-.".parse::<Input>(),
+			Err(ParseError::NoClosedLoop(ends)) if ends.is_empty()
+		));
+		assert!(matches!(
+			"...".parse::<Input>(),
+			Err(ParseError::Invalid(_))
+		));
+
+		// a pipe fits, but its loop is broken
+		let Err(ParseError::NoClosedLoop(ends)) = "\
+			.....\n\
+			.S-7.\n\
+			.|.|.\n\
+			.L-..\n"
+			.parse::<Input>()
+		else {
+			panic!("the loop shouldn't close")
+		};
+		assert_eq!(
+			ParseError::NoClosedLoop(ends.clone()).to_string(),
+			"none of the 1 possible pipes at the start tile form a closed loop; with Se, the \
+			pipe at [3, 2] dangles E"
+		);
+		assert_eq!(
+			ends,
+			[(
+				Pipe::Se,
+				DanglingEnd {
+					pos: [3, 2],
+					dir: Direction::E,
+					leads_to: Some(Tile::Ground),
+				}
+			)]
+		);
+
+		// both a loop to the east and a loop to the west pass through the start
+		assert!(matches!(
+			"F-7F-7\nL-SJ.|\n..L--J\n".parse::<Input>(),
+			Err(ParseError::AmbiguousStart([1, 2], _))
+		));
+	}
+
+	#[test]
+	fn multiple_loops() {
+		let map = "\
+			.....F7\n\
+			.S-7.LJ\n\
+			.|.|.-.\n\
+			.L-J...\n";
+
+		let input = read_input(map);
+		let mut loop_lens = input.loops().iter().map(|l| l.len()).collect::<Vec<_>>();
+		loop_lens.sort_unstable();
+		assert_eq!(loop_lens, [4, 8]);
+
+		assert_eq!(
+			input.dangling_ends(),
+			[
+				DanglingEnd {
+					pos: [2, 5],
+					dir: Direction::E,
+					leads_to: Some(Tile::Ground),
+				},
+				DanglingEnd {
+					pos: [2, 5],
+					dir: Direction::W,
+					leads_to: Some(Tile::Ground),
+				},
+			]
+		);
+
+		let input = read_input(&read_file!("input.txt"));
+		assert!(input.loops().iter().any(|l| l.len() == pt1(&input) * 2));
+
+		// a long chain is only walked once, not once from each of its tiles
+		let map = ["S7|", "LJ|"]
+			.into_iter()
+			.chain(std::iter::repeat_n("..|", 100_000))
+			.collect::<Vec<_>>()
+			.join("\n");
+		assert_eq!(
+			read_input(&map).loops(),
+			[vec![[0, 0], [1, 0], [1, 1], [0, 1]]]
+		);
+	}
 }
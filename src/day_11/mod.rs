@@ -15,11 +15,16 @@ fn read_input(input: &str) -> Image {
 	input.parse().unwrap()
 }
 
+/// The telescope's image: where the galaxies are, and which rows and columns are empty
 #[derive(Debug)]
-struct Image {
+pub struct Image {
 	galaxy_locs: Vec<[usize; 2]>,
-	empty_rows: Vec<bool>,
-	empty_cols: Vec<bool>,
+	/// `n_empty_rows_before[ri]` is the number of empty rows above row `ri`. Has one
+	/// more element than there are rows.
+	n_empty_rows_before: Vec<usize>,
+	/// `n_empty_cols_before[ci]` is the number of empty columns left of column `ci`. Has
+	/// one more element than there are columns.
+	n_empty_cols_before: Vec<usize>,
 }
 
 impl FromStr for Image {
@@ -50,48 +55,153 @@ impl FromStr for Image {
 		}
 		let width = n_tiles / height;
 
-		let empty_rows = (0..height).map(|ri| !occupied_rows.contains(&ri)).collect();
-		let empty_cols = (0..width).map(|ci| !occupied_cols.contains(&ci)).collect();
+		let prefix_counts = |len, occupied: HashSet<usize>| {
+			std::iter::once(0)
+				.chain((0..len).scan(0, |n_empty, i| {
+					*n_empty += usize::from(!occupied.contains(&i));
+					Some(*n_empty)
+				}))
+				.collect()
+		};
 
 		Ok(Self {
 			galaxy_locs,
-			empty_rows,
-			empty_cols,
+			n_empty_rows_before: prefix_counts(height, occupied_rows),
+			n_empty_cols_before: prefix_counts(width, occupied_cols),
 		})
 	}
 }
 
-fn get_distances(img: &Image, expansion_factor: usize) -> usize {
-	let Image {
-		galaxy_locs,
-		empty_rows,
-		empty_cols,
-	} = img;
-
-	let mut net_dist = 0;
-	for (i, &p1) in galaxy_locs.iter().enumerate().skip(1) {
-		for &p2 in galaxy_locs.iter().take(i) {
-			// one shortest path is just to go straight horizontal and then straight
-			// vertical, which obviously has the length computed as `dist` below
-
-			let [r1, c1] = p1;
-			let [r2, c2] = p2;
-
-			let r_min = r1.min(r2);
-			let r_max = r1.max(r2);
-			let c_min = c1.min(c2);
-			let c_max = c1.max(c2);
-
-			let dist = (r_max - r_min)
-				+ (r_min..r_max).filter(|&ri| empty_rows[ri]).count() * (expansion_factor - 1)
-				+ (c_max - c_min)
-				+ (c_min..c_max).filter(|&ci| empty_cols[ci]).count() * (expansion_factor - 1);
-
-			net_dist += dist;
+/// Where each row (or column) of the image starts once each empty one at index `i` has
+/// been replaced by `factor(i)` of them. Has one more element than there are rows (or
+/// columns); the last is the expanded height (or width).
+fn expanded_offsets(n_empty_before: &[usize], factor: impl Fn(usize) -> usize) -> Vec<usize> {
+	std::iter::once(0)
+		.chain(
+			n_empty_before
+				.iter()
+				.zip(&n_empty_before[1..])
+				.enumerate()
+				.scan(0, |offset, (i, (n_before, n_through))| {
+					*offset += if n_through > n_before { factor(i) } else { 1 };
+					Some(*offset)
+				}),
+		)
+		.collect()
+}
+
+impl Image {
+	/// Expands every empty row `ri` into `row_factor(ri)` rows and every empty column
+	/// `ci` into `col_factor(ci)` columns
+	pub fn expand(
+		&self,
+		row_factor: impl Fn(usize) -> usize,
+		col_factor: impl Fn(usize) -> usize,
+	) -> Universe {
+		let row_offsets = expanded_offsets(&self.n_empty_rows_before, row_factor);
+		let col_offsets = expanded_offsets(&self.n_empty_cols_before, col_factor);
+
+		Universe {
+			galaxy_locs: self
+				.galaxy_locs
+				.iter()
+				.map(|&[ri, ci]| [row_offsets[ri], col_offsets[ci]])
+				.collect(),
 		}
 	}
 
-	net_dist
+	/// Expands every empty row and column into `expansion_factor` of them
+	pub fn expand_uniformly(&self, expansion_factor: usize) -> Universe {
+		self.expand(|_| expansion_factor, |_| expansion_factor)
+	}
+}
+
+/// An image whose empty rows and columns have been expanded
+#[derive(Debug)]
+pub struct Universe {
+	/// The expanded coordinates of each galaxy, in the same order as in the `Image`
+	galaxy_locs: Vec<[usize; 2]>,
+}
+
+impl Universe {
+	/// The distance between the `i`th and `j`th galaxies (in reading order)
+	fn distance(&self, i: usize, j: usize) -> usize {
+		// one shortest path is just to go straight horizontal and then straight vertical
+		let [r1, c1] = self.galaxy_locs[i];
+		let [r2, c2] = self.galaxy_locs[j];
+		r1.abs_diff(r2) + c1.abs_diff(c2)
+	}
+
+	/// The sum of the distances between every pair of galaxies
+	pub fn sum_of_distances(&self) -> usize {
+		// Manhattan distance splits into a row part and a column part, each of which only
+		// depends on the galaxies' coordinates along that axis
+		let (rows, cols) = self.galaxy_locs.iter().map(|&[ri, ci]| (ri, ci)).unzip();
+		sum_of_pairwise_diffs(rows) + sum_of_pairwise_diffs(cols)
+	}
+
+	/// The galaxy closest to the `i`th galaxy (in reading order), and its distance from
+	/// it. `None` if there's only one galaxy, or no `i`th galaxy.
+	pub fn nearest_neighbor(&self, i: usize) -> Option<(usize, usize)> {
+		self.galaxy_locs.get(i)?;
+		(0..self.galaxy_locs.len())
+			.filter(|&j| j != i)
+			.map(|j| (j, self.distance(i, j)))
+			.min_by_key(|&(_, dist)| dist)
+	}
+
+	/// The pair of galaxies farthest from each other, and their distance. `None` if
+	/// there are fewer than two galaxies.
+	pub fn farthest_pair(&self) -> Option<([usize; 2], usize)> {
+		if self.galaxy_locs.len() < 2 {
+			return None;
+		}
+
+		// `|r1 - r2| + |c1 - c2|` is the larger of `|(r1 + c1) - (r2 + c2)|` and
+		// `|(r1 - c1) - (r2 - c2)|`, so the farthest pair is the one that's farthest apart
+		// along one of the two diagonals. (We add `max_col` to keep `r - c` nonnegative.)
+		let max_col = self.galaxy_locs.iter().map(|&[_, ci]| ci).max()?;
+
+		let spread = |diag_coord: &dyn Fn([usize; 2]) -> usize| {
+			let coords = || {
+				self.galaxy_locs
+					.iter()
+					.map(|&loc| diag_coord(loc))
+					.enumerate()
+			};
+			let (i_min, lo) = coords().min_by_key(|&(_, x)| x)?;
+			let (i_max, hi) = coords().max_by_key(|&(_, x)| x)?;
+			Some(([i_min, i_max], hi - lo))
+		};
+
+		[
+			spread(&|[ri, ci]| ri + ci),
+			spread(&|[ri, ci]| ri + max_col - ci),
+		]
+		.into_iter()
+		.flatten()
+		.max_by_key(|&(_, dist)| dist)
+	}
+}
+
+/// The sum of `|x_i - x_j|` over all pairs `i < j`
+fn sum_of_pairwise_diffs(mut xs: Vec<usize>) -> usize {
+	// once sorted, each `x` is the larger of the pair when paired with each of the `k`
+	// elements before it, so it contributes `k * x - (sum of those elements)`
+	xs.sort_unstable();
+
+	let mut total = 0;
+	let mut prefix_sum = 0;
+	for (k, x) in xs.into_iter().enumerate() {
+		total += k * x - prefix_sum;
+		prefix_sum += x;
+	}
+
+	total
+}
+
+fn get_distances(img: &Image, expansion_factor: usize) -> usize {
+	img.expand_uniformly(expansion_factor).sum_of_distances()
 }
 // end::setup[]
 
@@ -130,4 +240,59 @@ mod test {
 			(pt2, 779_032_247_216),
 		);
 	}
+
+	#[test]
+	fn pairwise() {
+		for (input, expansion_factor) in [
+			(read_file!("sample_input.txt"), 10),
+			(read_file!("input.txt"), 1_000_000),
+		] {
+			let img = read_input(&input);
+			let universe = img.expand_uniformly(expansion_factor);
+			let n = universe.galaxy_locs.len();
+			let pairwise_sum = (0..n)
+				.flat_map(|i| (0..i).map(move |j| (i, j)))
+				.map(|(i, j)| universe.distance(i, j))
+				.sum::<usize>();
+			assert_eq!(pairwise_sum, get_distances(&img, expansion_factor));
+		}
+
+		let universe = read_input(&read_file!("sample_input.txt")).expand_uniformly(2);
+		assert_eq!(universe.distance(4, 8), 9);
+		assert_eq!(universe.distance(0, 6), 15);
+	}
+
+	#[test]
+	fn non_uniform_expansion() {
+		let img = read_input(&read_file!("sample_input.txt"));
+
+		// empty rows are 3 and 7; empty columns are 2, 5, and 8
+		let universe = img.expand(|_| 1, |_| 1);
+		assert_eq!(universe.galaxy_locs[0], [0, 3]);
+		assert_eq!(universe.galaxy_locs[8], [9, 4]);
+
+		let universe = img.expand(|ri| ri, |_| 0);
+		assert_eq!(universe.galaxy_locs[0], [0, 2]);
+		assert_eq!(universe.galaxy_locs[8], [17, 3]);
+
+		// the row and column parts of the distances are independent of each other
+		let cols_only = img.expand(|_| 1, |_| 10).sum_of_distances();
+		let rows_only = img.expand(|_| 10, |_| 1).sum_of_distances();
+		assert_eq!(
+			cols_only + rows_only - get_distances(&img, 1),
+			get_distances(&img, 10)
+		);
+
+		let universe = img.expand_uniformly(2);
+		assert_eq!(universe.nearest_neighbor(4), Some((2, 5)));
+		assert_eq!(universe.nearest_neighbor(9), None);
+		let ([i, j], dist) = universe.farthest_pair().unwrap();
+		assert_eq!(universe.distance(i, j), dist);
+		let n_galaxies = universe.galaxy_locs.len();
+		let brute_force_max = (0..n_galaxies)
+			.flat_map(|i| (0..i).map(move |j| (i, j)))
+			.map(|(i, j)| universe.distance(i, j))
+			.max();
+		assert_eq!(Some(dist), brute_force_max);
+	}
 }
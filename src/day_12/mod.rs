@@ -6,6 +6,8 @@ use crate::{
 use ndarray::prelude::*;
 use std::{collections::HashMap, ops::ControlFlow, str::FromStr};
 
+pub mod nonogram;
+
 fn ans_for_input(input: &str) -> Answer<usize, usize> {
 	let springs = read_input(input);
 	(12, (pt1(&springs), pt2(&springs))).into()
@@ -25,7 +27,7 @@ fn read_input(input: &str) -> Vec<Row> {
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[repr(u8)]
-enum Spring {
+pub enum Spring {
 	Operational,
 	Damaged,
 }
@@ -55,8 +57,10 @@ impl TryFrom<char> for Tile {
 	}
 }
 
+/// A row of the condition records: the springs, some of them unknown, and the lengths of
+/// the groups of damaged springs, in order
 #[derive(Debug)]
-struct Row {
+pub struct Row {
 	tiles: Vec<Tile>,
 	lengths: Vec<usize>,
 }
@@ -177,7 +181,10 @@ fn count_solns<'a>(
 }
 
 impl Row {
-	fn count_solns<'a>(&'a self, cache: &mut HashMap<(&'a [Tile], &'a [usize]), usize>) -> usize {
+	/// Matrix such that `mat[[r, c]]` means a run of damaged tiles of length c can start
+	/// at the r^th tile. (The first column, c == 0, is entirely false.) Has one more row
+	/// than there are tiles.
+	fn valid_run_lens(&self) -> Array2<bool> {
 		let Self { tiles, .. } = self;
 		let mut tiles = tiles.clone();
 		// Add a known operational tile to the end of the row. This won't affect the
@@ -186,8 +193,6 @@ impl Row {
 		// a bit.
 		tiles.push(Tile::Known(Spring::Operational));
 
-		// Matrix such that `mat[[r, c]]` means a run of damaged tiles of length c can
-		// start at the r^th tile. (The first column, c == 0, is entirely false.)
 		let mut valid_run_lens =
 			Array2::from_shape_simple_fn((tiles.len(), tiles.len() + 1), || false);
 
@@ -209,31 +214,88 @@ impl Row {
 			}
 		}
 
-		count_solns(&self.tiles, &self.lengths, valid_run_lens.view(), cache)
+		valid_run_lens
+	}
+
+	fn count_solns<'a>(&'a self, cache: &mut HashMap<(&'a [Tile], &'a [usize]), usize>) -> usize {
+		count_solns(
+			&self.tiles,
+			&self.lengths,
+			self.valid_run_lens().view(),
+			cache,
+		)
+	}
+
+	/// Table such that `table[[i, g]]` is the number of ways to arrange the groups from
+	/// the g^th onward in the tiles from the i^th onward, assuming the tile before the
+	/// i^th is operational. Has two more rows than there are tiles (the last two are for
+	/// when we've run off the end of the row) and one more column than there are groups.
+	fn count_table(&self) -> Array2<usize> {
+		let Self { tiles, lengths } = self;
+		let n_tiles = tiles.len();
+		let n_groups = lengths.len();
+
+		let valid_run_lens = self.valid_run_lens();
+		let mut table = Array2::zeros((n_tiles + 2, n_groups + 1));
+		table[[n_tiles, n_groups]] = 1;
+		table[[n_tiles + 1, n_groups]] = 1;
+
+		for i in (0..n_tiles).rev() {
+			for g in 0..=n_groups {
+				// either this tile is operational...
+				let mut count = if tiles[i] == Tile::Known(Spring::Damaged) {
+					0
+				} else {
+					table[[i + 1, g]]
+				};
+
+				// ...or the g^th group starts here (and is followed by an operational tile)
+				if let Some(&len) = lengths.get(g)
+					&& len <= n_tiles - i
+					&& valid_run_lens[[i, len]]
+				{
+					count += table[[i + len + 1, g + 1]];
+				}
+
+				table[[i, g]] = count;
+			}
+		}
+
+		table
 	}
 }
 
-// end::setup[]
+/// How to count a row's arrangements
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strategy {
+	/// Recursively split the groups in half, memoizing on the sub-slices of tiles and
+	/// groups. Around 80 times slower than `DynamicProgramming` on the unfolded input (see
+	/// the benchmarks), and only kept to compare against.
+	DivideAndConquer,
+	/// Fill in `Row::count_table` from the end of the row to the start
+	DynamicProgramming,
+}
 
-// tag::pt1[]
-fn pt1(rows: impl IntoIterator<Item = &Row>) -> usize {
-	let mut cache = HashMap::new();
+fn count_all_solns<'a>(rows: impl IntoIterator<Item = &'a Row>, strategy: Strategy) -> usize {
 	rows.into_iter()
-		.map(|row| row.count_solns(&mut cache))
+		.map(|row| match strategy {
+			// a fresh cache per row keeps the cache from growing with the number of rows
+			Strategy::DivideAndConquer => row.count_solns(&mut HashMap::new()),
+			Strategy::DynamicProgramming => row.count_table()[[0, 0]],
+		})
 		.sum()
 }
-// end::pt1[]
 
-// tag::pt2[]
-fn pt2(rows: &[Row]) -> usize {
-	let new_rows = rows
-		.iter()
+/// Repeats each row's tiles `factor` times (separated by unknown tiles), and its groups
+/// `factor` times
+fn unfold(rows: &[Row], factor: usize) -> Vec<Row> {
+	rows.iter()
 		.map(|Row { tiles, lengths }| {
 			let mut new_row = Row {
 				tiles: Vec::new(),
 				lengths: Vec::new(),
 			};
-			for i in 0..5 {
+			for i in 0..factor {
 				if i > 0 {
 					new_row.tiles.push(Tile::Unknown);
 				}
@@ -242,18 +304,204 @@ fn pt2(rows: &[Row]) -> usize {
 			}
 			new_row
 		})
-		.collect::<Vec<_>>();
+		.collect()
+}
+
+/// How many times part two unfolds each row
+const UNFOLD_FACTOR: usize = 5;
+
+/// The total number of arrangements of `rows` once each has been unfolded `factor` times
+pub fn count_unfolded(rows: &[Row], factor: usize, strategy: Strategy) -> usize {
+	count_all_solns(&unfold(rows, factor), strategy)
+}
+// end::setup[]
 
-	pt1(&new_rows)
+// tag::pt1[]
+fn pt1(rows: impl IntoIterator<Item = &Row>) -> usize {
+	count_all_solns(rows, Strategy::DynamicProgramming)
+}
+// end::pt1[]
+
+// tag::pt2[]
+fn pt2(rows: &[Row]) -> usize {
+	count_unfolded(rows, UNFOLD_FACTOR, Strategy::DynamicProgramming)
 }
 // end::pt2[]
 
+impl Row {
+	/// The tiles at which the `group_idx`th group could start, given that every tile
+	/// before the `tile_idx`th has already been decided (and that tiles from there until
+	/// the group's start will be operational)
+	fn group_starts<'a>(
+		&'a self,
+		[tile_idx, group_idx]: [usize; 2],
+		valid_run_lens: ArrayView2<'a, bool>,
+	) -> impl Iterator<Item = usize> + 'a {
+		let len = self.lengths[group_idx];
+		let last_start = (self.tiles.len() + 1).saturating_sub(len);
+		(tile_idx..last_start)
+			// we can't skip over a damaged tile
+			.take_while(move |&i| {
+				i == tile_idx || self.tiles[i - 1] != Tile::Known(Spring::Damaged)
+			})
+			.filter(move |&i| valid_run_lens[[i, len]])
+	}
+
+	/// Lazily iterates over every arrangement of damaged springs consistent with the row,
+	/// each as a string of `#` (damaged) and `.` (operational)
+	pub fn arrangements(&self) -> Arrangements<'_> {
+		Arrangements {
+			row: self,
+			valid_run_lens: self.valid_run_lens(),
+			counts: self.count_table(),
+			stack: vec![([0, 0], String::with_capacity(self.tiles.len()))],
+		}
+	}
+
+	/// The `k`th arrangement (in the order `arrangements` yields them), or `None` if
+	/// there are `k` or fewer arrangements
+	pub fn nth_arrangement(&self, mut k: usize) -> Option<String> {
+		let valid_run_lens = self.valid_run_lens();
+		let counts = self.count_table();
+
+		let mut arrangement = String::with_capacity(self.tiles.len());
+		let mut tile_idx = 0;
+
+		// at each group, skip over the starting tiles that lead to fewer than `k`
+		// arrangements, taking `k` down by that many as we go
+		'groups: for group_idx in 0..self.lengths.len() {
+			let len = self.lengths[group_idx];
+			for start in self.group_starts([tile_idx, group_idx], valid_run_lens.view()) {
+				let next_tile_idx = start + len + 1;
+				let n = counts[[next_tile_idx, group_idx + 1]];
+				if k < n {
+					push_group(&mut arrangement, self.tiles.len(), tile_idx, start, len);
+					tile_idx = next_tile_idx;
+					continue 'groups;
+				}
+				k -= n;
+			}
+			return None;
+		}
+
+		let rest = &self.tiles[tile_idx.min(self.tiles.len())..];
+		if k > 0 || rest.contains(&Tile::Known(Spring::Damaged)) {
+			return None;
+		}
+		fill_operational(&mut arrangement, self.tiles.len());
+
+		Some(arrangement)
+	}
+
+	/// Picks an arrangement uniformly at random. `random_below(n)` must return a number
+	/// chosen uniformly at random from `0..n`. `None` if there are no arrangements.
+	pub fn sample_arrangement(&self, random_below: impl FnOnce(usize) -> usize) -> Option<String> {
+		let n = self.count_table()[[0, 0]];
+		if n == 0 {
+			return None;
+		}
+		self.nth_arrangement(random_below(n))
+	}
+
+	/// The unknown tiles whose spring is the same in every arrangement, along with that
+	/// spring. Empty if there are no arrangements at all.
+	pub fn forced_tiles(&self) -> Vec<(usize, Spring)> {
+		let n_total = self.count_table()[[0, 0]];
+		if n_total == 0 {
+			return Vec::new();
+		}
+
+		self.tiles
+			.iter()
+			.enumerate()
+			.filter(|&(_, &tile)| tile == Tile::Unknown)
+			.filter_map(|(i, _)| {
+				let mut tiles = self.tiles.clone();
+				tiles[i] = Tile::Known(Spring::Damaged);
+				let row = Row {
+					tiles,
+					lengths: self.lengths.clone(),
+				};
+				match row.count_table()[[0, 0]] {
+					0 => Some((i, Spring::Operational)),
+					n if n == n_total => Some((i, Spring::Damaged)),
+					_ => None,
+				}
+			})
+			.collect()
+	}
+}
+
+/// Appends operational tiles up to `start`, the group of `len` damaged tiles, and then
+/// the operational tile that must follow the group (unless the row ends first)
+fn push_group(arrangement: &mut String, n_tiles: usize, tile_idx: usize, start: usize, len: usize) {
+	arrangement.extend(std::iter::repeat_n('.', start - tile_idx));
+	arrangement.extend(std::iter::repeat_n('#', len));
+	if start + len < n_tiles {
+		arrangement.push('.');
+	}
+}
+
+fn fill_operational(arrangement: &mut String, n_tiles: usize) {
+	let n_remaining = n_tiles - arrangement.len();
+	arrangement.extend(std::iter::repeat_n('.', n_remaining));
+}
+
+/// Lazily yields each arrangement of a row, via depth-first search over where each group
+/// starts. Branches with no arrangements are pruned using the row's counts, so every
+/// branch we go down yields at least one arrangement.
+pub struct Arrangements<'a> {
+	row: &'a Row,
+	valid_run_lens: Array2<bool>,
+	counts: Array2<usize>,
+	/// `([tile_idx, group_idx], arrangement so far)`
+	stack: Vec<([usize; 2], String)>,
+}
+
+impl Iterator for Arrangements<'_> {
+	type Item = String;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		let row = self.row;
+		let n_tiles = row.tiles.len();
+
+		while let Some(([tile_idx, group_idx], mut arrangement)) = self.stack.pop() {
+			if group_idx == row.lengths.len() {
+				if row.tiles[tile_idx.min(n_tiles)..].contains(&Tile::Known(Spring::Damaged)) {
+					continue;
+				}
+				fill_operational(&mut arrangement, n_tiles);
+				return Some(arrangement);
+			}
+
+			let len = row.lengths[group_idx];
+			let starts = row
+				.group_starts([tile_idx, group_idx], self.valid_run_lens.view())
+				.collect::<Vec<_>>();
+
+			// push in reverse so that earlier starts are popped (and yielded) first
+			for start in starts.into_iter().rev() {
+				let next = [start + len + 1, group_idx + 1];
+				if self.counts[next] == 0 {
+					continue;
+				}
+				let mut arrangement = arrangement.clone();
+				push_group(&mut arrangement, n_tiles, tile_idx, start, len);
+				self.stack.push((next, arrangement));
+			}
+		}
+
+		None
+	}
+}
+
 #[cfg(test)]
 mod test {
 	#![allow(unused_imports)]
 
 	use super::*;
 	use crate::{run_test, run_tests};
+	use ::test::{black_box, Bencher};
 
 	#[test]
 	fn sample() {
@@ -269,7 +517,96 @@ mod test {
 		run_tests(
 			&*read_input(&read_file!("input.txt")),
 			(pt1, 7716),
-			(pt2, 779_032_247_216),
+			(pt2, 18_716_325_559_999),
 		);
 	}
+
+	#[test]
+	fn arrangements() {
+		fn group_lens(arrangement: &str) -> Vec<usize> {
+			arrangement
+				.split('.')
+				.filter(|s| !s.is_empty())
+				.map(str::len)
+				.collect()
+		}
+
+		for row in read_input(&read_file!("sample_input.txt")) {
+			let n = row.count_table()[[0, 0]];
+			let arrangements = row.arrangements().collect::<Vec<_>>();
+			assert_eq!(arrangements.len(), n);
+
+			for (k, arrangement) in arrangements.iter().enumerate() {
+				assert_eq!(group_lens(arrangement), row.lengths);
+				for (&tile, c) in row.tiles.iter().zip(arrangement.chars()) {
+					assert!(tile == Tile::Unknown || tile == Tile::try_from(c).unwrap());
+				}
+				assert_eq!(row.nth_arrangement(k).as_ref(), Some(arrangement));
+			}
+			assert_eq!(row.nth_arrangement(n), None);
+
+			let mut sorted = arrangements.clone();
+			sorted.sort_unstable();
+			sorted.dedup();
+			assert_eq!(sorted.len(), n);
+
+			assert!(arrangements.contains(&row.sample_arrangement(|n| n / 2).unwrap()));
+		}
+
+		let row = "???.### 1,1,3".parse::<Row>().unwrap();
+		assert_eq!(row.arrangements().collect::<Vec<_>>(), ["#.#.###"]);
+	}
+
+	#[test]
+	fn forced_tiles() {
+		use Spring::*;
+
+		let row = "???.### 1,1,3".parse::<Row>().unwrap();
+		assert_eq!(
+			row.forced_tiles(),
+			[(0, Damaged), (1, Operational), (2, Damaged)]
+		);
+
+		let row = "?###???????? 3,2,1".parse::<Row>().unwrap();
+		assert_eq!(row.forced_tiles(), [(0, Operational), (4, Operational)]);
+
+		let row = "??? 4".parse::<Row>().unwrap();
+		assert_eq!(row.forced_tiles(), []);
+	}
+
+	#[test]
+	fn strategies() {
+		use Strategy::*;
+
+		for input in [read_file!("sample_input.txt"), read_file!("input.txt")] {
+			let rows = read_input(&input);
+			for factor in 1..=UNFOLD_FACTOR {
+				assert_eq!(
+					count_unfolded(&rows, factor, DivideAndConquer),
+					count_unfolded(&rows, factor, DynamicProgramming)
+				);
+			}
+		}
+
+		let rows = read_input(&read_file!("sample_input.txt"));
+		run_test(&*unfold(&rows, 1), (pt1, 21));
+		run_test(&*unfold(&rows, 5), (pt1, 525_152));
+	}
+
+	fn bench_strategy(b: &mut Bencher, strategy: Strategy) {
+		let rows = read_input(&read_file!("input.txt"));
+		b.iter(|| count_unfolded(black_box(&rows), UNFOLD_FACTOR, strategy));
+	}
+
+	// `strategies` checks that the two agree; these time them on pt2's fully unfolded rows
+
+	#[bench]
+	fn pt2_divide_and_conquer(b: &mut Bencher) {
+		bench_strategy(b, Strategy::DivideAndConquer);
+	}
+
+	#[bench]
+	fn pt2_dynamic_programming(b: &mut Bencher) {
+		bench_strategy(b, Strategy::DynamicProgramming);
+	}
 }
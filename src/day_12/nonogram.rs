@@ -0,0 +1,226 @@
+//! A nonogram is a grid of cells, each filled or empty, with a clue for every row and
+//! column listing the lengths of its runs of filled cells. Each row and column is thus
+//! exactly a row of springs, with filled cells as damaged springs.
+
+use super::{Row, Spring, Tile};
+use crate::{AocError, AocResult};
+use ndarray::prelude::*;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Nonogram {
+	row_clues: Vec<Vec<usize>>,
+	col_clues: Vec<Vec<usize>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Solutions {
+	None,
+	Unique(Array2<Spring>),
+	/// Two of the solutions (there may be more)
+	Multiple([Array2<Spring>; 2]),
+}
+
+/// The lengths of the runs of damaged springs in `line`
+fn clue(line: ArrayView1<Spring>) -> Vec<usize> {
+	let mut lengths = Vec::new();
+	let mut run_len = 0;
+	for &spring in line.iter().chain(&[Spring::Operational]) {
+		match spring {
+			Spring::Damaged => run_len += 1,
+			Spring::Operational if run_len > 0 => {
+				lengths.push(run_len);
+				run_len = 0;
+			}
+			Spring::Operational => {}
+		}
+	}
+	lengths
+}
+
+/// Fills in the tiles of `line` that are the same in every arrangement consistent with
+/// the line and `lengths`. Returns whether any tile was filled in, or `Err` if there are
+/// no consistent arrangements. `(axis, idx)`, like `("row", 3)`, names the line in that
+/// error.
+fn propagate_line(
+	mut line: ArrayViewMut1<Tile>,
+	lengths: &[usize],
+	(axis, idx): (&str, usize),
+) -> AocResult<bool> {
+	let row = Row {
+		tiles: line.to_vec(),
+		lengths: lengths.to_vec(),
+	};
+
+	if row.count_table()[[0, 0]] == 0 {
+		return Err(AocError::Other(format!(
+			"no arrangement of {axis} {idx} fits its clue {lengths:?}"
+		)));
+	}
+
+	let forced = row.forced_tiles();
+	for &(i, spring) in &forced {
+		line[i] = Tile::Known(spring);
+	}
+
+	Ok(!forced.is_empty())
+}
+
+impl Nonogram {
+	pub fn new(row_clues: Vec<Vec<usize>>, col_clues: Vec<Vec<usize>>) -> Self {
+		Self {
+			row_clues,
+			col_clues,
+		}
+	}
+
+	/// The nonogram whose clues describe `picture`
+	pub fn from_picture(picture: ArrayView2<Spring>) -> Self {
+		Self::new(
+			picture.rows().into_iter().map(clue).collect(),
+			picture.columns().into_iter().map(clue).collect(),
+		)
+	}
+
+	fn dim(&self) -> (usize, usize) {
+		(self.row_clues.len(), self.col_clues.len())
+	}
+
+	/// Repeatedly fills in every tile that's forced by its row's clue or its column's
+	/// clue, until there's nothing left to fill in. `Err` if some row or column can't be
+	/// completed.
+	fn propagate(&self, grid: &mut Array2<Tile>) -> AocResult<()> {
+		let mut changed = true;
+		while changed {
+			changed = false;
+			let rows = grid.rows_mut().into_iter().zip(&self.row_clues);
+			for (ri, (line, lengths)) in rows.enumerate() {
+				changed |= propagate_line(line, lengths, ("row", ri))?;
+			}
+			let cols = grid.columns_mut().into_iter().zip(&self.col_clues);
+			for (ci, (line, lengths)) in cols.enumerate() {
+				changed |= propagate_line(line, lengths, ("column", ci))?;
+			}
+		}
+		Ok(())
+	}
+
+	/// Propagates constraints as far as they go, and then, if that wasn't enough to solve
+	/// the puzzle, guesses the first unknown tile each way and recurses. Stops once two
+	/// solutions have been found.
+	fn search(&self, mut grid: Array2<Tile>, solutions: &mut Vec<Array2<Spring>>) {
+		if solutions.len() >= 2 || self.propagate(&mut grid).is_err() {
+			return;
+		}
+
+		match grid
+			.indexed_iter()
+			.find(|&(_, &tile)| tile == Tile::Unknown)
+		{
+			None => solutions.push(grid.mapv(|tile| match tile {
+				Tile::Known(spring) => spring,
+				Tile::Unknown => unreachable!("all tiles are known"),
+			})),
+			Some((idx, _)) => {
+				for spring in [Spring::Damaged, Spring::Operational] {
+					let mut grid = grid.clone();
+					grid[idx] = Tile::Known(spring);
+					self.search(grid, solutions);
+				}
+			}
+		}
+	}
+
+	/// Up to two of the pictures whose clues are this nonogram's
+	pub fn solve(&self) -> Solutions {
+		let mut solutions = Vec::new();
+		self.search(Array2::from_elem(self.dim(), Tile::Unknown), &mut solutions);
+
+		let mut solutions = solutions.into_iter();
+		match (solutions.next(), solutions.next()) {
+			(None, _) => Solutions::None,
+			(Some(soln), None) => Solutions::Unique(soln),
+			(Some(soln1), Some(soln2)) => Solutions::Multiple([soln1, soln2]),
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn picture(s: &str) -> Array2<Spring> {
+		let lines = s.lines().collect::<Vec<_>>();
+		let springs = lines
+			.iter()
+			.flat_map(|line| line.chars())
+			.map(|c| match c {
+				'#' => Spring::Damaged,
+				_ => Spring::Operational,
+			})
+			.collect();
+		Array2::from_shape_vec((lines.len(), lines[0].len()), springs).unwrap()
+	}
+
+	#[test]
+	fn unique() {
+		let heart = picture(
+			"\
+			.#.#.\n\
+			#####\n\
+			#####\n\
+			.###.\n\
+			..#..\n",
+		);
+		let nonogram = Nonogram::from_picture(heart.view());
+		assert_eq!(
+			nonogram.row_clues,
+			[vec![1, 1], vec![5], vec![5], vec![3], vec![1]]
+		);
+		assert_eq!(
+			nonogram.col_clues,
+			[vec![2], vec![4], vec![4], vec![4], vec![2]]
+		);
+		assert_eq!(nonogram.solve(), Solutions::Unique(heart));
+
+		// propagation alone only fills in the middle two columns of the first and third
+		// rows; the rest needs backtracking
+		let zigzag = picture(
+			"\
+			...#\n\
+			#.#.\n\
+			.###\n\
+			.#..\n",
+		);
+		let nonogram = Nonogram::from_picture(zigzag.view());
+		let mut grid = Array2::from_elem(nonogram.dim(), Tile::Unknown);
+		nonogram.propagate(&mut grid).unwrap();
+		assert!(grid.iter().any(|&tile| tile == Tile::Unknown));
+		assert_eq!(nonogram.solve(), Solutions::Unique(zigzag));
+	}
+
+	#[test]
+	fn multiple() {
+		let nonogram = Nonogram::new(vec![vec![1], vec![1]], vec![vec![1], vec![1]]);
+		let Solutions::Multiple([soln1, soln2]) = nonogram.solve() else {
+			panic!("expected multiple solutions");
+		};
+		assert_ne!(soln1, soln2);
+		for soln in [soln1, soln2] {
+			assert_eq!(Nonogram::from_picture(soln.view()), nonogram);
+		}
+	}
+
+	#[test]
+	fn none() {
+		let nonogram = Nonogram::new(vec![vec![2], vec![2]], vec![vec![1], vec![1]]);
+		assert_eq!(nonogram.solve(), Solutions::None);
+
+		let nonogram = Nonogram::new(vec![vec![3]], vec![vec![1], vec![1]]);
+		assert_eq!(nonogram.solve(), Solutions::None);
+		let mut grid = Array2::from_elem(nonogram.dim(), Tile::Unknown);
+		let Err(AocError::Other(err)) = nonogram.propagate(&mut grid) else {
+			panic!("a row of two can't fit a run of three")
+		};
+		assert_eq!(err, "no arrangement of row 0 fits its clue [3]");
+	}
+}
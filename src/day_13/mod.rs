@@ -1,6 +1,6 @@
 // tag::setup[]
 use crate::{error::AocResult, read_file, Answer, AocError};
-use ndarray::{prelude::*, Zip};
+use ndarray::prelude::*;
 use std::str::FromStr;
 
 fn ans_for_input(input: &str) -> Answer<usize, usize> {
@@ -57,79 +57,96 @@ enum Direction {
 	Vertical,
 }
 
+/// A line the grid could be reflected over, and how far the grid is from being a
+/// perfect reflection over it
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Reflection {
+	dir: Direction,
+	/// The number of rows above (if `dir` is horizontal) or columns left of (if
+	/// vertical) the line
+	n_before: usize,
+	/// The pairs of cells, one on either side of the line, that are each other's
+	/// reflection but differ. Flipping either cell of every pair makes the grid a perfect
+	/// reflection over the line.
+	mismatches: Vec<[[usize; 2]; 2]>,
+}
+
 impl Grid {
 	fn grid(&self) -> ArrayView2<'_, Tile> {
 		self.0.view()
 	}
 
-	fn check_symmetry(&self, axis: Axis, index: usize, smudged: bool) -> Result<(), ()> {
+	fn reflection(&self, dir: Direction, n_before: usize) -> Reflection {
 		let grid = self.grid();
 
-		// we will right this wrong immediately, but it makes for cleaner code
-		let mut lower = index + 1;
-		let mut upper = index;
-
-		if lower >= grid.len_of(axis) {
-			return Err(());
-		}
+		let (axis, other_axis) = match dir {
+			Direction::Horizontal => (Axis(0), Axis(1)),
+			Direction::Vertical => (Axis(1), Axis(0)),
+		};
+		let to_idx = |i, j| match dir {
+			Direction::Horizontal => [i, j],
+			Direction::Vertical => [j, i],
+		};
 
-		let mut did_change_one = false;
-
-		while lower > 0 && upper < grid.len_of(axis) - 1 {
-			lower -= 1;
-			upper += 1;
-
-			let n_diff = Zip::from(grid.index_axis(axis, lower))
-				.and(grid.index_axis(axis, upper))
-				.fold(0, |acc, x, y| acc + usize::from(x != y));
-
-			match n_diff {
-				0 => {}
-				1 if smudged => {
-					if did_change_one {
-						return Err(());
-					}
-					did_change_one = true;
-				}
-				1.. => return Err(()),
-			}
+		// pair up the lanes moving outward from the line, stopping when we reach an edge
+		let n_pairs = n_before.min(grid.len_of(axis) - n_before);
+		let mismatches = (0..n_pairs)
+			.map(|d| (n_before - 1 - d, n_before + d))
+			.flat_map(|(lower, upper)| {
+				(0..grid.len_of(other_axis))
+					.map(move |j| [to_idx(lower, j), to_idx(upper, j)])
+					.filter(|&[idx1, idx2]| grid[idx1] != grid[idx2])
+			})
+			.collect();
+
+		Reflection {
+			dir,
+			n_before,
+			mismatches,
 		}
+	}
 
-		if smudged && !did_change_one {
-			Err(())
-		} else {
-			Ok(())
-		}
+	/// Every line the grid could be reflected over (horizontal lines first), whether or
+	/// not the grid is actually symmetric across it
+	fn reflections(&self) -> impl Iterator<Item = Reflection> + '_ {
+		[Direction::Horizontal, Direction::Vertical]
+			.into_iter()
+			.flat_map(move |dir| {
+				let len = match dir {
+					Direction::Horizontal => self.grid().nrows(),
+					Direction::Vertical => self.grid().ncols(),
+				};
+				(1..len).map(move |n_before| self.reflection(dir, n_before))
+			})
 	}
 
-	fn find_symmetry(&self, dir: Direction, smudged: bool) -> Option<(Direction, usize)> {
-		let axis = match dir {
-			Direction::Horizontal => Axis(0),
-			Direction::Vertical => Axis(1),
-		};
-		(0..self.grid().len_of(axis)).find_map(|i| {
-			self.check_symmetry(axis, i, smudged)
-				.map(|()| (dir, i + 1))
-				.ok()
-		})
+	/// The lines over which the grid would be a perfect reflection after fixing exactly
+	/// `n_smudges` smudges
+	fn reflections_with_smudges(&self, n_smudges: usize) -> impl Iterator<Item = Reflection> + '_ {
+		self.reflections()
+			.filter(move |reflection| reflection.mismatches.len() == n_smudges)
 	}
 
-	fn axis_of_symmetry(&self, smudged: bool) -> (Direction, usize) {
-		self.find_symmetry(Direction::Horizontal, smudged)
-			.or_else(|| self.find_symmetry(Direction::Vertical, smudged))
-			.unwrap()
+	fn axis_of_symmetry(&self, n_smudges: usize) -> AocResult<Reflection> {
+		self.reflections_with_smudges(n_smudges)
+			.next()
+			.ok_or_else(|| {
+				AocError::Other(format!(
+					"no line of reflection with exactly {n_smudges} smudges"
+				))
+			})
 	}
 }
 
-fn symmetry_num(grids: &[Grid], smudged: bool) -> usize {
+fn symmetry_num(grids: &[Grid], n_smudges: usize) -> AocResult<usize> {
 	grids
 		.iter()
 		.map(|g| {
-			let (axis, i) = g.axis_of_symmetry(smudged);
-			match axis {
-				Direction::Horizontal => i * 100,
-				Direction::Vertical => i,
-			}
+			let Reflection { dir, n_before, .. } = g.axis_of_symmetry(n_smudges)?;
+			Ok(match dir {
+				Direction::Horizontal => n_before * 100,
+				Direction::Vertical => n_before,
+			})
 		})
 		.sum()
 }
@@ -137,13 +154,13 @@ fn symmetry_num(grids: &[Grid], smudged: bool) -> usize {
 
 // tag::pt1[]
 fn pt1(grids: &[Grid]) -> usize {
-	symmetry_num(grids, false)
+	symmetry_num(grids, 0).unwrap()
 }
 // end::pt1[]
 
 // tag::pt2[]
 fn pt2(grids: &[Grid]) -> usize {
-	symmetry_num(grids, true)
+	symmetry_num(grids, 1).unwrap()
 }
 // end::pt2[]
 
@@ -171,4 +188,44 @@ mod test {
 			(pt2, 42996),
 		);
 	}
+
+	#[test]
+	fn reflections() {
+		let grids = read_input(&read_file!("sample_input.txt"));
+
+		assert_eq!(
+			grids[0].reflections_with_smudges(0).collect::<Vec<_>>(),
+			[Reflection {
+				dir: Direction::Vertical,
+				n_before: 5,
+				mismatches: vec![],
+			}]
+		);
+		assert_eq!(
+			grids[0].axis_of_symmetry(1).unwrap(),
+			Reflection {
+				dir: Direction::Horizontal,
+				n_before: 3,
+				mismatches: vec![[[0, 0], [5, 0]]],
+			}
+		);
+		assert_eq!(
+			grids[1].axis_of_symmetry(1).unwrap().mismatches,
+			[[[0, 4], [1, 4]]]
+		);
+
+		// every line shows up exactly once, with its own number of smudges
+		for grid in &grids {
+			let [nr, nc] = grid.grid().shape().try_into().unwrap();
+			assert_eq!(grid.reflections().count(), (nr - 1) + (nc - 1));
+			let n_by_smudges = (0..nr * nc)
+				.map(|k| grid.reflections_with_smudges(k).count())
+				.sum::<usize>();
+			assert_eq!(n_by_smudges, (nr - 1) + (nc - 1));
+		}
+
+		let grid = "#.\n.#".parse::<Grid>().unwrap();
+		assert!(grid.axis_of_symmetry(0).is_err());
+		assert!(symmetry_num(&[grid], 0).is_err());
+	}
 }
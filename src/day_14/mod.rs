@@ -2,10 +2,19 @@
 use crate::{read_file, Answer, AocError, Cast};
 use ndarray::prelude::*;
 use num::range_step_inclusive;
-use std::{collections::HashMap, str::FromStr};
+use packed::PackedGrid;
+use program::Program;
+use std::{
+	fmt::{self, Write},
+	str::FromStr,
+};
+use strum_macros::EnumCount;
+
+mod packed;
+mod program;
 
 fn ans_for_input(input: &str) -> Answer<usize, usize> {
-	let mut grid = read_input(input);
+	let mut grid = PackedGrid::from(&read_input(input));
 	(14, (pt1(&mut grid.clone()), pt2(&mut grid))).into()
 }
 
@@ -24,7 +33,8 @@ enum Tile {
 	Square,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumCount)]
+#[repr(u8)]
 enum Direction {
 	N,
 	S,
@@ -32,6 +42,53 @@ enum Direction {
 	W,
 }
 
+impl From<Direction> for usize {
+	fn from(value: Direction) -> Self {
+		value as _
+	}
+}
+
+impl TryFrom<char> for Direction {
+	type Error = AocError;
+
+	fn try_from(c: char) -> Result<Self, Self::Error> {
+		use Direction::*;
+		Ok(match c {
+			'N' => N,
+			'S' => S,
+			'E' => E,
+			'W' => W,
+			_ => return Err(AocError::Other(format!("invalid direction {c:?}"))),
+		})
+	}
+}
+
+/// The load that a round rock at `(ri, ci)` puts on the `dir` edge of a grid of shape
+/// `(nr, nc)`
+fn weight(dir: Direction, (nr, nc): (usize, usize), (ri, ci): (usize, usize)) -> usize {
+	use Direction::*;
+
+	match dir {
+		N => nr - ri,
+		S => ri + 1,
+		W => nc - ci,
+		E => ci + 1,
+	}
+}
+
+/// A way of storing the rocks on the platform
+trait Platform: Clone {
+	/// Rolls every round rock as far as it'll go toward `dir`
+	fn tilt(&mut self, dir: Direction);
+
+	fn load(&self, dir: Direction) -> usize;
+
+	/// The positions of the round rocks, one bit per tile in row-major order. Square
+	/// rocks never move, so this is enough to tell apart any two grids that a given grid
+	/// can be tilted into.
+	fn fingerprint(&self) -> Vec<u64>;
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 struct Grid(Array2<Tile>);
 
@@ -58,7 +115,24 @@ impl FromStr for Grid {
 	}
 }
 
-impl Grid {
+impl fmt::Display for Grid {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let Self(grid) = self;
+		for row in grid.rows() {
+			for tile in row {
+				f.write_char(match tile {
+					Tile::Empty => '.',
+					Tile::Round => 'O',
+					Tile::Square => '#',
+				})?;
+			}
+			f.write_char('\n')?;
+		}
+		Ok(())
+	}
+}
+
+impl Platform for Grid {
 	fn tilt(&mut self, dir: Direction) {
 		#![allow(clippy::similar_names)]
 
@@ -67,8 +141,11 @@ impl Grid {
 		let Self(grid) = self;
 		let (nr, nc) = grid.dim();
 
-		if nr < 2 || nc < 2 {
-			return;
+		// nothing can roll along an axis only one tile long
+		match dir {
+			N | S if nr < 2 => return,
+			E | W if nc < 2 => return,
+			_ => {}
 		}
 
 		// inclusive
@@ -103,19 +180,19 @@ impl Grid {
 
 				let [mut ri, mut ci] = orig_idx;
 
-				while match dir {
-					N => ri > 0,
-					S => ri < nr - 1,
-					E => ci < nc - 1,
-					W => ci > 0,
-				} && grid[[ri, ci]] == Tile::Empty
-				{
-					match dir {
-						N => ri -= 1,
-						S => ri += 1,
-						E => ci += 1,
-						W => ci -= 1,
+				// roll until the next tile over is the edge of the grid or isn't empty
+				loop {
+					let next_idx = match dir {
+						N if ri > 0 => [ri - 1, ci],
+						S if ri < nr - 1 => [ri + 1, ci],
+						E if ci < nc - 1 => [ri, ci + 1],
+						W if ci > 0 => [ri, ci - 1],
+						_ => break,
+					};
+					if grid[next_idx] != Tile::Empty {
+						break;
 					}
+					[ri, ci] = next_idx;
 				}
 
 				grid.swap(orig_idx, [ri, ci]);
@@ -124,67 +201,38 @@ impl Grid {
 	}
 
 	fn load(&self, dir: Direction) -> usize {
-		use Direction::*;
-
 		let Self(grid) = self;
-		let (nr, nc) = grid.dim();
-
-		let get_weight = |(ri, ci): (usize, usize)| match dir {
-			N => nr - ri,
-			S => ri + 1,
-			W => nc - ci,
-			E => ci + 1,
-		};
 
 		grid.indexed_iter()
 			.filter(|&(_, &tile)| (tile == Tile::Round))
-			.map(|(idx, _)| get_weight(idx))
+			.map(|(idx, _)| weight(dir, grid.dim(), idx))
 			.sum::<usize>()
 	}
+
+	fn fingerprint(&self) -> Vec<u64> {
+		let Self(grid) = self;
+		let mut bits = vec![0; grid.len().div_ceil(64)];
+		for (i, &tile) in grid.iter().enumerate() {
+			if tile == Tile::Round {
+				bits[i / 64] |= 1 << (i % 64);
+			}
+		}
+		bits
+	}
 }
 // end::setup[]
 
 // tag::pt1[]
-fn pt1(grid: &mut Grid) -> usize {
+fn pt1<P: Platform>(grid: &mut P) -> usize {
 	grid.tilt(Direction::N);
 	grid.load(Direction::N)
 }
 // end::pt1[]
 
 // tag::pt2[]
-fn pt2(grid: &mut Grid) -> usize {
-	use Direction::*;
-
-	let mut seen_grid_idxs = HashMap::new();
-
-	let mut i = 0_usize;
-	let n_cycles = 1_000_000_000;
-	let mut found_loop = false;
-
-	while i < n_cycles {
-		match seen_grid_idxs.get(&grid.0) {
-			Some(&loop_start_idx) => {
-				if !found_loop {
-					found_loop = true;
-					let cycle_len = i - loop_start_idx;
-					let remaining_rounds = n_cycles - i;
-					let remainder = remaining_rounds % cycle_len;
-					// fast forward to end
-					i = n_cycles - remainder;
-				}
-			}
-			None => {
-				seen_grid_idxs.insert(grid.0.clone(), i);
-			}
-		}
-
-		for dir in [N, W, S, E] {
-			grid.tilt(dir);
-		}
-
-		i += 1;
-	}
-	grid.load(N)
+fn pt2<P: Platform>(grid: &mut P) -> usize {
+	*grid = Program::spin_cycle().repeated(1_000_000_000).run(grid);
+	grid.load(Direction::N)
 }
 // end::pt2[]
 
@@ -193,7 +241,7 @@ mod test {
 	#![allow(unused_imports)]
 
 	use super::*;
-	use crate::{run_test, run_tests};
+	use crate::{cycle::Trajectory, run_test, run_tests};
 
 	#[test]
 	fn sample() {
@@ -208,4 +256,27 @@ mod test {
 		run_test(&mut input.clone(), (pt1, 110_779));
 		run_test(&mut input, (pt2, 86069));
 	}
+
+	#[test]
+	fn cycle_detection() {
+		let spin_cycle = |grid: &Grid| Program::spin_cycle().run(grid);
+
+		for input in [read_file!("sample_input.txt"), read_file!("input.txt")] {
+			let grid = read_input(&input);
+
+			let mut brent = Trajectory::brent(grid.clone(), spin_cycle, usize::MAX).unwrap();
+			let mut hashed =
+				Trajectory::by_fingerprint(grid, spin_cycle, Grid::fingerprint, usize::MAX)
+					.unwrap();
+			assert_eq!((brent.mu(), brent.lambda()), (hashed.mu(), hashed.lambda()));
+
+			let n = brent.mu() + 3 * brent.lambda() + 1;
+			assert_eq!(brent.nth_state(n), hashed.nth_state(n));
+			assert_eq!(brent.nth_state(n), brent.nth_state(brent.mu() + 1));
+		}
+
+		let grid = read_input(&read_file!("sample_input.txt"));
+		let trajectory = Trajectory::brent(grid, spin_cycle, usize::MAX).unwrap();
+		assert_eq!((trajectory.mu(), trajectory.lambda()), (3, 7));
+	}
 }
@@ -0,0 +1,238 @@
+//! Square rocks never move, so they split every row and column into fixed runs of tiles
+//! that round rocks can roll along but never leave. Tilting a run just piles all its
+//! round rocks up against one end, so it's enough to count them; where they were within
+//! the run doesn't matter.
+
+use super::{weight, Direction, Grid, Platform, Tile};
+use crate::EnumMap;
+use std::rc::Rc;
+use strum::EnumCount;
+
+/// For each direction, the runs of tiles between square rocks (and the edges of the
+/// grid), as row-major indices ordered from the end that rocks roll toward
+type Runs = EnumMap<{ Direction::COUNT }, Direction, Vec<Vec<usize>>>;
+
+/// Only the round rocks are stored per grid, as a bitset; everything that follows from
+/// the square rocks is computed once and shared between clones
+#[derive(Debug, Clone)]
+pub(super) struct PackedGrid {
+	dim: (usize, usize),
+	round: Vec<u64>,
+	runs: Rc<Runs>,
+}
+
+fn get_bit(bits: &[u64], i: usize) -> bool {
+	bits[i / 64] & (1 << (i % 64)) != 0
+}
+
+fn set_bit(bits: &mut [u64], i: usize, value: bool) {
+	if value {
+		bits[i / 64] |= 1 << (i % 64);
+	} else {
+		bits[i / 64] &= !(1 << (i % 64));
+	}
+}
+
+impl From<&Grid> for PackedGrid {
+	fn from(Grid(grid): &Grid) -> Self {
+		let (nr, nc) = grid.dim();
+		let idx = |ri, ci| ri * nc + ci;
+
+		let n_words = grid.len().div_ceil(64);
+		let mut round = vec![0; n_words];
+		let mut square = vec![0; n_words];
+		for (i, &tile) in grid.iter().enumerate() {
+			match tile {
+				Tile::Round => set_bit(&mut round, i, true),
+				Tile::Square => set_bit(&mut square, i, true),
+				Tile::Empty => {}
+			}
+		}
+
+		let split_lanes = |lanes: Vec<Vec<usize>>| -> Vec<Vec<usize>> {
+			lanes
+				.iter()
+				.flat_map(|lane| lane.split(|&i| get_bit(&square, i)))
+				.filter(|run| !run.is_empty())
+				.map(<[usize]>::to_vec)
+				.collect()
+		};
+		let reversed = |runs: &[Vec<usize>]| -> Vec<Vec<usize>> {
+			runs.iter()
+				.map(|run| run.iter().rev().copied().collect())
+				.collect()
+		};
+
+		let north = split_lanes(
+			(0..nc)
+				.map(|ci| (0..nr).map(|ri| idx(ri, ci)).collect())
+				.collect(),
+		);
+		let west = split_lanes(
+			(0..nr)
+				.map(|ri| (0..nc).map(|ci| idx(ri, ci)).collect())
+				.collect(),
+		);
+		let south = reversed(&north);
+		let east = reversed(&west);
+
+		Self {
+			dim: (nr, nc),
+			round,
+			runs: Rc::new(EnumMap::new([north, south, east, west])),
+		}
+	}
+}
+
+impl PackedGrid {
+	/// The row-major indices of the round rocks
+	fn round_indices(&self) -> impl Iterator<Item = usize> + '_ {
+		self.round.iter().enumerate().flat_map(|(word_idx, &word)| {
+			let mut word = word;
+			std::iter::from_fn(move || {
+				if word == 0 {
+					return None;
+				}
+				let bit = word.trailing_zeros() as usize;
+				word &= word - 1;
+				Some(word_idx * 64 + bit)
+			})
+		})
+	}
+}
+
+impl Platform for PackedGrid {
+	fn tilt(&mut self, dir: Direction) {
+		for run in &self.runs[dir] {
+			let n_round = run.iter().filter(|&&i| get_bit(&self.round, i)).count();
+			for (k, &i) in run.iter().enumerate() {
+				set_bit(&mut self.round, i, k < n_round);
+			}
+		}
+	}
+
+	fn load(&self, dir: Direction) -> usize {
+		let (_, nc) = self.dim;
+		self.round_indices()
+			.map(|i| weight(dir, self.dim, (i / nc, i % nc)))
+			.sum()
+	}
+
+	fn fingerprint(&self) -> Vec<u64> {
+		self.round.clone()
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::super::{pt2, read_input, Program};
+	use super::*;
+	use crate::{read_file, Cast, XorShift};
+	use ::test::{black_box, Bencher};
+
+	#[test]
+	fn matches_grid() {
+		use Direction::*;
+
+		for input in [read_file!("sample_input.txt"), read_file!("input.txt")] {
+			let mut grid = read_input(&input);
+			let mut packed = PackedGrid::from(&grid);
+
+			for dir in [N, W, S, E, E, S, W, N, S, N, W, E]
+				.into_iter()
+				.cycle()
+				.take(40)
+			{
+				grid.tilt(dir);
+				packed.tilt(dir);
+				assert_eq!(grid.fingerprint(), packed.fingerprint());
+				for dir in [N, S, E, W] {
+					assert_eq!(grid.load(dir), packed.load(dir));
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn matches_grid_on_random_platforms() {
+		use Direction::*;
+
+		let mut rng = XorShift::new(0x9e37_79b9_7f4a_7c15);
+		let mut random_below = |n| rng.below(n);
+
+		for _ in 0..200 {
+			let (nr, nc) = (1 + random_below(12), 1 + random_below(12));
+			let (pct_round, pct_square) = (random_below(60), random_below(40));
+			let platform = (0..nr)
+				.map(|_| {
+					(0..nc)
+						.map(|_| match random_below(100) {
+							x if x < pct_round => 'O',
+							x if x < pct_round + pct_square => '#',
+							_ => '.',
+						})
+						.chain(['\n'])
+						.collect::<String>()
+				})
+				.collect::<String>();
+
+			let mut grid = platform.parse::<Grid>().unwrap();
+			let mut packed = PackedGrid::from(&grid);
+			for _ in 0..20 {
+				let dir = [N, S, E, W][random_below(4).cast::<usize>()];
+				grid.tilt(dir);
+				packed.tilt(dir);
+				assert_eq!(grid.fingerprint(), packed.fingerprint(), "{platform}");
+				for dir in [N, S, E, W] {
+					assert_eq!(grid.load(dir), packed.load(dir), "{platform}");
+				}
+			}
+
+			let program = Program::spin_cycle().repeated(1000);
+			assert_eq!(
+				program.run(&grid).fingerprint(),
+				program.run(&packed).fingerprint(),
+				"{platform}"
+			);
+		}
+	}
+
+	fn bench_spin_cycles<P: Platform>(b: &mut Bencher, platform: &P) {
+		use Direction::*;
+
+		b.iter(|| {
+			let mut platform = platform.clone();
+			for _ in 0..10 {
+				for dir in [N, W, S, E] {
+					platform.tilt(dir);
+				}
+			}
+			black_box(platform)
+		});
+	}
+
+	// ten spin cycles are nothing but tilts; pt2 also pays for fingerprinting every state
+	// until the cycle shows up
+
+	#[bench]
+	fn spin_cycles_grid(b: &mut Bencher) {
+		bench_spin_cycles(b, &read_input(&read_file!("input.txt")));
+	}
+
+	#[bench]
+	fn spin_cycles_packed(b: &mut Bencher) {
+		bench_spin_cycles(b, &PackedGrid::from(&read_input(&read_file!("input.txt"))));
+	}
+
+	#[bench]
+	fn pt2_grid(b: &mut Bencher) {
+		let grid = read_input(&read_file!("input.txt"));
+		b.iter(|| pt2(&mut black_box(grid.clone())));
+	}
+
+	#[bench]
+	fn pt2_packed(b: &mut Bencher) {
+		let grid = PackedGrid::from(&read_input(&read_file!("input.txt")));
+		b.iter(|| pt2(&mut black_box(grid.clone())));
+	}
+}
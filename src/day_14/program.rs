@@ -0,0 +1,291 @@
+//! Tilt programs: whitespace-separated directions, each optionally repeated with `*n`,
+//! and parenthesized groups that can be repeated the same way. So the spin cycle is
+//! `N W S E`, part two is `(N W S E)*1000000000`, and `E*3 N` tilts east three times
+//! and then north.
+
+use super::{Direction, Platform};
+use crate::{cycle::Trajectory, AocError, AocResult};
+use std::{iter::Peekable, str::CharIndices, str::FromStr};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Command {
+	Tilt(Direction),
+	Repeat(Program, usize),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct Program(Vec<Command>);
+
+struct Parser<'a> {
+	s: &'a str,
+	chars: Peekable<CharIndices<'a>>,
+}
+
+impl Parser<'_> {
+	fn skip_whitespace(&mut self) {
+		while self.chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+	}
+
+	fn pos(&mut self) -> usize {
+		self.chars.peek().map_or(self.s.len(), |&(i, _)| i)
+	}
+
+	/// Commands up to the end of the input or an unmatched `)`, which isn't consumed. A
+	/// group without a repetition count is just its commands, spliced in.
+	fn program(&mut self) -> AocResult<Program> {
+		let mut commands = Vec::new();
+		loop {
+			self.skip_whitespace();
+			match self.chars.peek() {
+				None | Some((_, ')')) => return Ok(Program(commands)),
+				Some(&(i, '(')) => {
+					self.chars.next();
+					let group = self.program()?;
+					if self.chars.next_if(|&(_, c)| c == ')').is_none() {
+						return Err(AocError::Other(format!("unclosed '(' at {i}")));
+					}
+					match self.repetitions()? {
+						Some(n) => commands.push(Command::Repeat(group, n)),
+						None => commands.extend(group.0),
+					}
+				}
+				Some(&(i, c)) => {
+					self.chars.next();
+					let tilt = Command::Tilt(Direction::try_from(c).map_err(|_| {
+						AocError::Other(format!("expected a direction at {i}, got {c:?}"))
+					})?);
+					commands.push(match self.repetitions()? {
+						Some(n) => Command::Repeat(Program(vec![tilt]), n),
+						None => tilt,
+					});
+				}
+			}
+		}
+	}
+
+	/// The `n` of a `*n` right after a command, if there is one
+	fn repetitions(&mut self) -> AocResult<Option<usize>> {
+		if self.chars.next_if(|&(_, c)| c == '*').is_none() {
+			return Ok(None);
+		}
+
+		let start = self.pos();
+		while self.chars.next_if(|(_, c)| c.is_ascii_digit()).is_some() {}
+		let digits = &self.s[start..self.pos()];
+		digits
+			.parse()
+			.map(Some)
+			.map_err(|_| AocError::Other(format!("expected a repetition count at {start}")))
+	}
+}
+
+impl FromStr for Program {
+	type Err = AocError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let mut parser = Parser {
+			s,
+			chars: s.char_indices().peekable(),
+		};
+		let program = parser.program()?;
+		match parser.chars.next() {
+			None => Ok(program),
+			Some((i, c)) => Err(AocError::Other(format!("unexpected {c:?} at {i}"))),
+		}
+	}
+}
+
+impl Program {
+	/// `N W S E`
+	pub(super) fn spin_cycle() -> Self {
+		use Direction::*;
+
+		Self([N, W, S, E].map(Command::Tilt).to_vec())
+	}
+
+	/// `(self)*n`
+	pub(super) fn repeated(self, n: usize) -> Self {
+		Self(vec![Command::Repeat(self, n)])
+	}
+
+	/// The grid after the whole program has run. Repeated commands only run until the
+	/// grid comes back to a state it was in before, then skip ahead to where they'd end
+	/// up, which can take up to twice as many runs as the cycle is long; a repeat that's
+	/// done before the grid comes back around just runs as many times as it says.
+	pub(super) fn run<P: Platform>(&self, grid: &P) -> P {
+		let mut grid = grid.clone();
+		for command in &self.0 {
+			match command {
+				&Command::Tilt(dir) => grid.tilt(dir),
+				Command::Repeat(program, n) => {
+					grid = match Trajectory::by_fingerprint(
+						grid,
+						|grid| program.run(grid),
+						P::fingerprint,
+						*n,
+					) {
+						Ok(mut trajectory) => trajectory.nth_state(*n),
+						Err(nth_state) => nth_state,
+					};
+				}
+			}
+		}
+		grid
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::super::{read_input, Grid};
+	use super::*;
+	use crate::read_file;
+	use std::{cell::Cell, rc::Rc};
+
+	/// A grid that counts how many times it's been tilted, across all its clones
+	#[derive(Clone)]
+	struct Counted {
+		grid: Grid,
+		tilts: Rc<Cell<usize>>,
+	}
+
+	impl Platform for Counted {
+		fn tilt(&mut self, dir: Direction) {
+			self.tilts.set(self.tilts.get() + 1);
+			self.grid.tilt(dir);
+		}
+
+		fn load(&self, dir: Direction) -> usize {
+			self.grid.load(dir)
+		}
+
+		fn fingerprint(&self) -> Vec<u64> {
+			self.grid.fingerprint()
+		}
+	}
+
+	impl Program {
+		/// The directions tilted in, in order, with every repetition spelled out
+		fn tilts(&self) -> Box<dyn Iterator<Item = Direction> + '_> {
+			Box::new(self.0.iter().flat_map(
+				|command| -> Box<dyn Iterator<Item = Direction> + '_> {
+					match command {
+						&Command::Tilt(dir) => Box::new(std::iter::once(dir)),
+						Command::Repeat(program, n) => {
+							Box::new((0..*n).flat_map(|_| program.tilts()))
+						}
+					}
+				},
+			))
+		}
+
+		/// The grid after each tilt, in order, for watching the program run one step at a
+		/// time. Repetitions aren't skipped, so this is as long as the program is.
+		fn trace<'a, P: Platform + 'a>(&'a self, grid: &P) -> impl Iterator<Item = P> + 'a {
+			self.tilts().scan(grid.clone(), |grid, dir| {
+				grid.tilt(dir);
+				Some(grid.clone())
+			})
+		}
+	}
+
+	#[test]
+	fn parse() {
+		use Direction::*;
+
+		let program = "E*3 N (W S)*2".parse::<Program>().unwrap();
+		assert_eq!(
+			program.tilts().collect::<Vec<_>>(),
+			[E, E, E, N, W, S, W, S]
+		);
+		assert_eq!("((N) S*2)*2".parse::<Program>().unwrap().tilts().count(), 6);
+		assert_eq!(
+			"(N W) ((S)) E".parse::<Program>().unwrap(),
+			Program::spin_cycle()
+		);
+		assert_eq!("".parse::<Program>().unwrap().tilts().count(), 0);
+
+		for bad in ["N X", "(N W", "N W)", "N*", "N *3", "*3"] {
+			assert!(bad.parse::<Program>().is_err(), "{bad:?}");
+		}
+	}
+
+	#[test]
+	fn run() {
+		use Direction::*;
+
+		let grid = read_input(&read_file!("sample_input.txt"));
+
+		let program = "(N W S E)*1000000000".parse::<Program>().unwrap();
+		assert_eq!(program, Program::spin_cycle().repeated(1_000_000_000));
+		assert_eq!(program.run(&grid).load(N), 64);
+
+		// the trace and the cycle-skipping run agree whenever the trace is short enough to
+		// spell out
+		let program = "(N W S E)*20 E*3 (S W)*5 N".parse::<Program>().unwrap();
+		let trace = program.trace(&grid).collect::<Vec<_>>();
+		assert_eq!(trace.len(), 4 * 20 + 3 + 2 * 5 + 1);
+		assert_eq!(trace.last(), Some(&program.run(&grid)));
+
+		let after_one_cycle = &trace[3];
+		assert_eq!(after_one_cycle, &Program::spin_cycle().run(&grid));
+		assert_eq!(
+			after_one_cycle.to_string(),
+			"\
+			.....#....\n\
+			....#...O#\n\
+			...OO##...\n\
+			.OO#......\n\
+			.....OOO#.\n\
+			.O#...O#.#\n\
+			....O#....\n\
+			......OOOO\n\
+			#...O###..\n\
+			#..OO#....\n"
+		);
+		assert_eq!(
+			[N, S, E, W].map(|dir| after_one_cycle.load(dir)),
+			[87, 111, 106, 92]
+		);
+		assert_eq!(
+			after_one_cycle.to_string().parse::<Grid>().unwrap(),
+			*after_one_cycle
+		);
+	}
+
+	#[test]
+	fn tilts_taken() {
+		for input in [read_file!("sample_input.txt"), read_file!("input.txt")] {
+			let grid = read_input(&input);
+			let tilts_taken = |program: &str| {
+				let counted = Counted {
+					grid: grid.clone(),
+					tilts: Rc::default(),
+				};
+				let program = program.parse::<Program>().unwrap();
+				assert_eq!(program.run(&counted).grid, program.run(&grid));
+				counted.tilts.get()
+			};
+
+			// repeats that finish before the grid comes back around run exactly as written
+			assert_eq!(tilts_taken("N W S E"), 4);
+			assert_eq!(tilts_taken("(N W S E)"), 4);
+			assert_eq!(tilts_taken("((N W S E)*2)*2"), 16);
+			assert_eq!(tilts_taken("(N W S E)*0 N*3"), 3);
+
+			// longer ones stop after about twice the cycle
+			let mut trajectory = Trajectory::by_fingerprint(
+				grid.clone(),
+				|grid| Program::spin_cycle().run(grid),
+				Grid::fingerprint,
+				usize::MAX,
+			)
+			.unwrap();
+			let cycle_end = trajectory.mu() + trajectory.lambda();
+			assert!(tilts_taken("(N W S E)*1000000000") <= 4 * 2 * cycle_end);
+			assert_eq!(
+				trajectory.nth_state(cycle_end - 1),
+				Program::spin_cycle().repeated(cycle_end - 1).run(&grid)
+			);
+		}
+	}
+}
@@ -1,26 +1,33 @@
 // tag::setup[]
-use crate::{
-	error::{AocResult, ToResultDefaultErr},
-	read_file, Answer, AocError, Cast,
-};
-use std::str::FromStr;
+use crate::{error::AocResult, read_file, Answer, AocError, Cast};
+use std::{borrow::Borrow, slice, str::FromStr};
 
-fn ans_for_input(input: &str) -> Answer<u32, u32> {
+fn ans_for_input(input: &str) -> Answer<u32, u64> {
 	let instrs = read_input(input);
 	(15, (pt1(&instrs), pt2(&instrs))).into()
 }
 
-pub fn ans() -> Answer<u32, u32> {
+pub fn ans() -> Answer<u32, u64> {
 	ans_for_input(&read_file!("input.txt"))
 }
 
 fn read_input(input: &str) -> Vec<Instr> {
+	parse_instrs(input).unwrap()
+}
+
+/// Parses the comma-separated steps of `input`, reporting errors by their byte offset in
+/// `input`
+fn parse_instrs(input: &str) -> AocResult<Vec<Instr>> {
+	let mut offset = 0;
 	input
-		.trim()
+		.trim_end()
 		.split(',')
-		.map(Instr::from_str)
-		.collect::<AocResult<_>>()
-		.unwrap()
+		.map(|step| {
+			let instr = Instr::parse(step, offset);
+			offset += step.len() + 1;
+			instr
+		})
+		.collect()
 }
 
 fn hash_into(h: &mut u32, b: u8) {
@@ -29,14 +36,152 @@ fn hash_into(h: &mut u32, b: u8) {
 	*h %= 256;
 }
 
+fn hash(bytes: &[u8]) -> u32 {
+	let mut h = 0;
+	for &b in bytes {
+		hash_into(&mut h, b);
+	}
+	h
+}
+
+const N_BOXES: usize = 256;
+
+/// A map laid out the way the HASHMAP procedure lays out lenses: [`N_BOXES`] boxes, with
+/// each key going in the box given by the HASH of its bytes. Within a box, entries stay
+/// in the order their keys were first inserted; inserting an existing key replaces its
+/// value in place, and removing a key shifts the entries behind it forward.
+///
+/// As with [`std::collections::HashMap`], keys that are equal must have equal bytes, and
+/// so must a key and any borrowed form of it used for lookup.
+#[derive(Debug, Clone)]
+pub struct HolidayMap<K, V> {
+	boxes: [Vec<(K, V)>; N_BOXES],
+	len: usize,
+}
+
+impl<K, V> Default for HolidayMap<K, V> {
+	fn default() -> Self {
+		Self {
+			boxes: std::array::from_fn(|_| Vec::new()),
+			len: 0,
+		}
+	}
+}
+
+impl<K: AsRef<[u8]> + Eq, V> HolidayMap<K, V> {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn len(&self) -> usize {
+		self.len
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.len == 0
+	}
+
+	fn box_idx<Q: AsRef<[u8]> + ?Sized>(key: &Q) -> usize {
+		hash(key.as_ref()).cast()
+	}
+
+	/// Returns the value that `key` had before, if any
+	pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+		let box_ = &mut self.boxes[Self::box_idx(&key)];
+		if let Some((_, v)) = box_.iter_mut().find(|(k, _)| *k == key) {
+			return Some(std::mem::replace(v, value));
+		}
+		box_.push((key, value));
+		self.len += 1;
+		None
+	}
+
+	pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+	where
+		K: Borrow<Q>,
+		Q: AsRef<[u8]> + Eq + ?Sized,
+	{
+		let box_ = &mut self.boxes[Self::box_idx(key)];
+		let i = box_.iter().position(|(k, _)| k.borrow() == key)?;
+		self.len -= 1;
+		Some(box_.remove(i).1)
+	}
+
+	pub fn get<Q>(&self, key: &Q) -> Option<&V>
+	where
+		K: Borrow<Q>,
+		Q: AsRef<[u8]> + Eq + ?Sized,
+	{
+		self.boxes[Self::box_idx(key)]
+			.iter()
+			.find(|(k, _)| k.borrow() == key)
+			.map(|(_, v)| v)
+	}
+
+	/// The entries box by box, and within each box in insertion order
+	pub fn iter(&self) -> Iter<'_, K, V> {
+		self.into_iter()
+	}
+}
+
+impl<K, V: Copy + Into<u64>> HolidayMap<K, V> {
+	/// The sum over all entries of (one plus the index of its box) times (one plus its
+	/// index within its box) times its value. Summed in `u64`, so that a few `u32` values
+	/// can't overflow it.
+	pub fn focusing_power(&self) -> u64 {
+		(1..)
+			.zip(&self.boxes)
+			.map(|(box_num, box_)| {
+				box_num
+					* (1..)
+						.zip(box_)
+						.map(|(slot_num, &(_, v))| slot_num * v.into())
+						.sum::<u64>()
+			})
+			.sum()
+	}
+}
+
+pub struct Iter<'a, K, V> {
+	boxes: slice::Iter<'a, Vec<(K, V)>>,
+	entries: slice::Iter<'a, (K, V)>,
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+	type Item = (&'a K, &'a V);
+
+	fn next(&mut self) -> Option<Self::Item> {
+		loop {
+			if let Some((k, v)) = self.entries.next() {
+				return Some((k, v));
+			}
+			self.entries = self.boxes.next()?.iter();
+		}
+	}
+}
+
+impl<'a, K, V> IntoIterator for &'a HolidayMap<K, V> {
+	type Item = (&'a K, &'a V);
+	type IntoIter = Iter<'a, K, V>;
+
+	fn into_iter(self) -> Self::IntoIter {
+		Iter {
+			boxes: self.boxes.iter(),
+			entries: [].iter(),
+		}
+	}
+}
+
 #[derive(Debug, Clone, Copy)]
 enum Operation {
 	Remove,
-	Add(u8),
+	Add(u32),
 }
 
 #[derive(Debug, Clone)]
 struct Instr {
+	/// The step exactly as it appeared in the input
+	step: Vec<u8>,
 	label: Vec<u8>,
 	op: Operation,
 }
@@ -45,50 +190,74 @@ impl FromStr for Instr {
 	type Err = AocError;
 
 	fn from_str(s: &str) -> Result<Self, Self::Err> {
-		let mut bytes = s.bytes();
+		Self::parse(s, 0)
+	}
+}
+
+impl Instr {
+	/// Parses `step`, which starts `offset` bytes into the input. A step is a label of
+	/// ASCII letters and digits, followed by either `-` or `=` and a positive focal
+	/// length.
+	fn parse(step: &str, offset: usize) -> AocResult<Self> {
+		let err = |i: usize, msg: String| {
+			AocError::Other(format!("{msg} at byte {} (in step {step:?})", offset + i))
+		};
 
-		let mut label = Vec::new();
-		let mut op_byte = 0;
+		if step.is_empty() {
+			return Err(err(0, "empty step".to_owned()));
+		}
+		let label_len = step
+			.find(|c: char| !c.is_ascii_alphanumeric())
+			.unwrap_or(step.len());
+		if label_len == 0 {
+			return Err(err(0, "empty label".to_owned()));
+		}
 
-		for b in bytes.by_ref() {
-			match b {
-				b'a'..=b'z' | b'A'..=b'Z' => {
-					label.push(b);
+		let (label, rest) = step.split_at(label_len);
+		let mut rest_chars = rest.chars();
+		let op = match rest_chars.next() {
+			Some('-') => Operation::Remove,
+			Some('=') => {
+				let digits = rest_chars.as_str();
+				let digits_start = label_len + 1;
+				if let Some((i, c)) = digits.char_indices().find(|(_, c)| !c.is_ascii_digit()) {
+					return Err(err(digits_start + i, format!("invalid digit {c:?}")));
+				}
+				if digits.is_empty() {
+					return Err(err(digits_start, "missing focal length".to_owned()));
 				}
-				_ => {
-					op_byte = b;
-					break;
+				let focal_len = digits
+					.parse()
+					.map_err(|_| err(digits_start, "focal length too large".to_owned()))?;
+				if focal_len == 0 {
+					return Err(err(
+						digits_start,
+						"focal length must be positive".to_owned(),
+					));
 				}
+				Operation::Add(focal_len)
+			}
+			Some(c) => return Err(err(label_len, format!("expected '-' or '=', got {c:?}"))),
+			None => {
+				return Err(err(
+					label_len,
+					"expected '-' or '=', got end of step".to_owned(),
+				))
 			}
-		}
-
-		let op = match op_byte {
-			b'-' => Operation::Remove,
-			b'=' => Operation::Add(bytes.next().to_result()? - b'0'),
-			b => return Err(AocError::Other(format!("invalid byte {b:?}"))),
 		};
-		if bytes.next().is_some() {
-			return Err(AocError::Other(format!("did not consume all of {s:?}")));
+		if let (Operation::Remove, Some(c)) = (op, rest_chars.next()) {
+			return Err(err(label_len + 1, format!("unexpected {c:?} after '-'")));
 		}
 
-		Ok(Self { label, op })
+		Ok(Self {
+			step: step.as_bytes().to_vec(),
+			label: label.as_bytes().to_vec(),
+			op,
+		})
 	}
-}
 
-impl Instr {
 	fn hash(&self) -> u32 {
-		let mut h = 0;
-		for &b in &self.label {
-			hash_into(&mut h, b);
-		}
-		match self.op {
-			Operation::Remove => hash_into(&mut h, b'-'),
-			Operation::Add(n) => {
-				hash_into(&mut h, b'=');
-				hash_into(&mut h, n + b'0');
-			}
-		}
-		h
+		hash(&self.step)
 	}
 }
 // end::setup[]
@@ -100,47 +269,17 @@ fn pt1(instrs: &[Instr]) -> u32 {
 // end::pt1[]
 
 // tag::pt2[]
-fn pt2(instrs: &[Instr]) -> u32 {
-	#[derive(Debug, Clone, PartialEq, Eq)]
-	struct Lens<'a> {
-		label: &'a [u8],
-		focal_len: u8,
-	}
-
-	let mut boxes = std::array::from_fn::<_, 256, _>(|_| Vec::<Lens>::new());
-
-	for Instr { label, op } in instrs {
-		let box_idx = {
-			let mut h = 0;
-			for &b in label {
-				hash_into(&mut h, b);
-			}
-			h.cast::<usize>()
-		};
-		let box_ = &mut boxes[box_idx];
-		match op {
-			Operation::Remove => {
-				if let Some(i) = box_.iter().position(|lens| lens.label == label) {
-					box_.remove(i);
-				}
-			}
-			&Operation::Add(focal_len) => match box_.iter_mut().find(|lens| lens.label == label) {
-				Some(lens) => lens.focal_len = focal_len,
-				None => box_.push(Lens { label, focal_len }),
-			},
-		}
-	}
-
-	(1..)
-		.zip(boxes)
-		.map(|(box_idx, box_)| {
-			box_idx
-				* ((1..)
-					.zip(box_)
-					.map(|(slot_idx, lens)| slot_idx * u32::from(lens.focal_len)))
-				.sum::<u32>()
+fn pt2(instrs: &[Instr]) -> u64 {
+	instrs
+		.iter()
+		.fold(HolidayMap::new(), |mut lenses, Instr { label, op, .. }| {
+			match *op {
+				Operation::Remove => lenses.remove(label.as_slice()),
+				Operation::Add(focal_len) => lenses.insert(label.as_slice(), focal_len),
+			};
+			lenses
 		})
-		.sum()
+		.focusing_power()
 }
 // end::pt2[]
 
@@ -149,7 +288,7 @@ mod test {
 	#![allow(unused_imports)]
 
 	use super::*;
-	use crate::{run_test, run_tests};
+	use crate::{run_test, run_tests, XorShift};
 
 	#[test]
 	fn sample() {
@@ -168,4 +307,115 @@ mod test {
 			(pt2, 243_747),
 		);
 	}
+
+	#[test]
+	fn parse() {
+		let instrs = parse_instrs("ab12=345,7-,x=10\n").unwrap();
+		assert_eq!(
+			instrs
+				.iter()
+				.map(|instr| (instr.label.as_slice(), instr.hash()))
+				.collect::<Vec<_>>(),
+			[
+				(&b"ab12"[..], hash(b"ab12=345")),
+				(b"7", hash(b"7-")),
+				(b"x", hash(b"x=10")),
+			]
+		);
+		assert!(matches!(instrs[0].op, Operation::Add(345)));
+		assert!(matches!(instrs[1].op, Operation::Remove));
+		assert_eq!(pt2(&instrs), 7 * 345 + 249 * 10);
+		// "rn" and "cm" share box 0, and their focusing power doesn't fit in a `u32`
+		assert_eq!(
+			pt2(&parse_instrs("rn=4000000000,cm=4000000000").unwrap()),
+			3 * 4_000_000_000
+		);
+
+		for (input, expected_err) in [
+			(
+				"rn=1,cm-,qp=x3",
+				"invalid digit 'x' at byte 12 (in step \"qp=x3\")",
+			),
+			("rn=1,,cm-", "empty step at byte 5 (in step \"\")"),
+			("rn=1,=3", "empty label at byte 5 (in step \"=3\")"),
+			(
+				"rn=1,cm",
+				"expected '-' or '=', got end of step at byte 7 (in step \"cm\")",
+			),
+			(
+				"rn=1,cm-2",
+				"unexpected '2' after '-' at byte 8 (in step \"cm-2\")",
+			),
+			(
+				"rn=0",
+				"focal length must be positive at byte 3 (in step \"rn=0\")",
+			),
+			("rn=", "missing focal length at byte 3 (in step \"rn=\")"),
+			(
+				"rn=99999999999",
+				"focal length too large at byte 3 (in step \"rn=99999999999\")",
+			),
+			(
+				"ab,rn!1",
+				"expected '-' or '=', got end of step at byte 2 (in step \"ab\")",
+			),
+			(
+				"rn-,qp!1",
+				"expected '-' or '=', got '!' at byte 6 (in step \"qp!1\")",
+			),
+		] {
+			let Err(AocError::Other(err)) = parse_instrs(input) else {
+				panic!("expected an error parsing {input:?}");
+			};
+			assert_eq!(err, expected_err);
+		}
+	}
+
+	#[test]
+	fn holiday_map() {
+		use indexmap::IndexMap;
+
+		let mut rng = XorShift::new(0x2545_f491_4f6c_dd1d);
+		let mut random_below = |n| rng.below(n);
+
+		let labels = (0..60)
+			.map(|i| format!("{}{}", ["rn", "cm", "qp", "pc", "ot", "ab"][i % 6], i / 6))
+			.collect::<Vec<_>>();
+
+		let mut map = HolidayMap::new();
+		let mut expected = IndexMap::new();
+		for _ in 0..5000 {
+			let label = labels[random_below(60).cast::<usize>()].as_str();
+			if random_below(3) == 0 {
+				assert_eq!(map.remove(label), expected.shift_remove(label));
+			} else {
+				let focal_len = random_below(9).cast::<u8>() + 1;
+				assert_eq!(
+					map.insert(label, focal_len),
+					expected.insert(label, focal_len)
+				);
+			}
+			assert_eq!(map.len(), expected.len());
+			assert_eq!(map.is_empty(), expected.is_empty());
+			assert_eq!(map.get(label), expected.get(label));
+
+			// within each box, the map's order is the insertion order
+			let box_of = |label: &str| hash(label.as_bytes()).cast::<usize>();
+			for box_idx in 0..N_BOXES {
+				let in_box = |&(label, _): &(&&str, &u8)| box_of(label) == box_idx;
+				assert!(map.iter().filter(in_box).eq(expected.iter().filter(in_box)));
+			}
+
+			let mut n_in_box = [0; N_BOXES];
+			let focusing_power = expected
+				.iter()
+				.map(|(label, &focal_len)| {
+					let box_idx = box_of(label);
+					n_in_box[box_idx] += 1;
+					(box_idx.cast::<u64>() + 1) * n_in_box[box_idx] * u64::from(focal_len)
+				})
+				.sum::<u64>();
+			assert_eq!(map.focusing_power(), focusing_power);
+		}
+	}
 }
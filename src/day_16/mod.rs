@@ -1,7 +1,7 @@
 // tag::setup[]
-use crate::{read_file, Answer, AocError};
+use crate::{read_file, Answer, AocError, AocResult, Cast};
 use ndarray::prelude::*;
-use std::{collections::HashSet, str::FromStr};
+use std::{collections::HashMap, str::FromStr};
 
 fn ans_for_input(input: &str) -> Answer<usize, usize> {
 	let board = read_input(input);
@@ -16,24 +16,56 @@ fn read_input(input: &str) -> Board {
 	input.parse().unwrap()
 }
 
-#[derive(Debug, Clone, Copy)]
+/// The elements that can sit on a tile. What a beam does on a tile is decided entirely by
+/// [`Tile::exits`], the direction(s) a photon leaves it in, and [`Tile::exit_pos`], the
+/// tile it leaves from, so a new element only needs to fill those in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum Tile {
 	Empty,
 	SplitterHorizontal,
 	SplitterVertical,
 	MirrorSlash,
 	MirrorBackslash,
+	/// Swallows every photon that enters it
+	Absorber,
+	/// Lets through photons heading its way, and swallows the rest
+	OneWayGate(Direction),
+	/// Sends photons on from its partner, the other portal with the same label, still
+	/// heading the same way
+	Portal {
+		label: char,
+		partner: [usize; 2],
+	},
+}
+
+/// What happens to photons that reach the edge of the board
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Edges {
+	/// Photons that leave the board are gone
+	Open,
+	/// Photons that leave the board come back in on the opposite side
+	Wrapping,
 }
 
+/// The contraption: the tiles, and what its edges do. Parsed boards have
+/// [`Edges::Open`] edges, as in the puzzle; see [`Board::with_edges`].
 #[derive(Debug)]
-struct Board(Array2<Tile>);
+pub struct Board {
+	tiles: Array2<Tile>,
+	edges: Edges,
+}
 
 impl FromStr for Board {
 	type Err = AocError;
 
+	/// Besides the puzzle's tiles, reads `#` as an absorber, `^v><` as one-way gates, and
+	/// uppercase letters as portals, each of which must appear exactly twice
 	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		use Direction::*;
+
 		let mut elems = Vec::new();
 		let mut height = 0;
+		let mut portals = HashMap::<char, Vec<usize>>::new();
 
 		for line in s.lines() {
 			for c in line.chars() {
@@ -43,6 +75,18 @@ impl FromStr for Board {
 					'|' => Tile::SplitterVertical,
 					'/' => Tile::MirrorSlash,
 					'\\' => Tile::MirrorBackslash,
+					'#' => Tile::Absorber,
+					'^' => Tile::OneWayGate(N),
+					'v' => Tile::OneWayGate(S),
+					'>' => Tile::OneWayGate(E),
+					'<' => Tile::OneWayGate(W),
+					'A'..='Z' => {
+						portals.entry(c).or_default().push(elems.len());
+						Tile::Portal {
+							label: c,
+							partner: [0, 0],
+						}
+					}
 					_ => return Err(AocError::Other(format!("invalid character {c:?}"))),
 				};
 				elems.push(tile);
@@ -51,112 +95,506 @@ impl FromStr for Board {
 		}
 		let width = elems.len() / height;
 
-		Ok(Self(Array2::from_shape_vec((height, width), elems)?))
+		for (label, idxs) in portals {
+			let &[i, j] = idxs.as_slice() else {
+				return Err(AocError::Other(format!(
+					"portal {label:?} appears {} times, not twice",
+					idxs.len()
+				)));
+			};
+			for (from, to) in [(i, j), (j, i)] {
+				elems[from] = Tile::Portal {
+					label,
+					partner: [to / width, to % width],
+				};
+			}
+		}
+
+		Ok(Self {
+			tiles: Array2::from_shape_vec((height, width), elems)?,
+			edges: Edges::Open,
+		})
 	}
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-enum Direction {
+#[repr(u8)]
+pub enum Direction {
 	N,
 	S,
 	E,
 	W,
 }
+
+impl From<Direction> for usize {
+	fn from(value: Direction) -> Self {
+		value as _
+	}
+}
+
+/// A photon on the tile at `pos`, `[row, col]`, having entered it heading `dir`
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-struct Photon {
-	pos: [usize; 2],
-	dir: Direction,
+pub struct Photon {
+	pub pos: [usize; 2],
+	pub dir: Direction,
 }
-impl Board {
-	fn n_energized(&self, start_pos: [usize; 2], start_dir: Direction) -> usize {
+
+/// The direction(s) a photon leaves a tile in
+#[derive(Debug, Clone, Copy)]
+enum Exits {
+	Absorbed,
+	One(Direction),
+	/// Split into two photons
+	Split(Direction, Direction),
+}
+
+impl Tile {
+	fn to_char(self) -> char {
 		use Direction::*;
 		use Tile::*;
 
-		let Self(board) = self;
-		let (nr, nc) = board.dim();
+		match self {
+			Empty => '.',
+			SplitterHorizontal => '-',
+			SplitterVertical => '|',
+			MirrorSlash => '/',
+			MirrorBackslash => '\\',
+			Absorber => '#',
+			OneWayGate(N) => '^',
+			OneWayGate(S) => 'v',
+			OneWayGate(E) => '>',
+			OneWayGate(W) => '<',
+			Portal { label, .. } => label,
+		}
+	}
+
+	fn exits(self, dir: Direction) -> Exits {
+		use Direction::*;
+		use Tile::*;
+
+		match self {
+			SplitterHorizontal if matches!(dir, N | S) => Exits::Split(E, W),
+			SplitterVertical if matches!(dir, E | W) => Exits::Split(S, N),
+			MirrorSlash => Exits::One(match dir {
+				N => E,
+				E => N,
+				S => W,
+				W => S,
+			}),
+			MirrorBackslash => Exits::One(match dir {
+				N => W,
+				W => N,
+				S => E,
+				E => S,
+			}),
+			Absorber => Exits::Absorbed,
+			OneWayGate(gate_dir) if gate_dir != dir => Exits::Absorbed,
+			_ => Exits::One(dir),
+		}
+	}
+
+	/// The tile that a photon which entered this one, at `pos`, leaves from
+	fn exit_pos(self, pos: [usize; 2]) -> [usize; 2] {
+		match self {
+			Tile::Portal { partner, .. } => partner,
+			_ => pos,
+		}
+	}
+}
+
+impl Board {
+	/// The same board with different edges
+	#[must_use]
+	pub fn with_edges(self, edges: Edges) -> Self {
+		Self { edges, ..self }
+	}
+
+	/// The next tile over from `pos` in `dir`, if it's on the board
+	fn advance(&self, [ri, ci]: [usize; 2], dir: Direction) -> Option<[usize; 2]> {
+		use Direction::*;
+
+		let (nr, nc) = self.tiles.dim();
+		Some(match (dir, self.edges) {
+			(N, _) if ri > 0 => [ri - 1, ci],
+			(S, _) if ri < nr - 1 => [ri + 1, ci],
+			(E, _) if ci < nc - 1 => [ri, ci + 1],
+			(W, _) if ci > 0 => [ri, ci - 1],
+			(_, Edges::Open) => return None,
+			(N, Edges::Wrapping) => [nr - 1, ci],
+			(S, Edges::Wrapping) => [0, ci],
+			(E, Edges::Wrapping) => [ri, 0],
+			(W, Edges::Wrapping) => [ri, nc - 1],
+		})
+	}
+
+	/// Follows the beam that enters the board at `start_pos` heading `start_dir`, and all
+	/// the beams it's split into
+	/// ## Errors
+	/// If `start_pos` isn't on the board
+	pub fn energize(
+		&self,
+		start_pos: [usize; 2],
+		start_dir: Direction,
+	) -> AocResult<Energized<'_>> {
+		let (nr, nc) = self.tiles.dim();
+		if self.tiles.get(start_pos).is_none() {
+			return Err(AocError::Other(format!(
+				"{start_pos:?} isn't on the {nr}x{nc} board"
+			)));
+		}
 
 		let mut photons = vec![Photon {
 			pos: start_pos,
 			dir: start_dir,
 		}];
 
-		let mut lit_tiles = Array2::from_shape_simple_fn(board.dim(), || false);
-
-		let mut seen_photons = HashSet::new();
+		let mut lit_tiles = Array2::from_elem(self.tiles.dim(), false);
+		let mut seen_photons = Array3::from_elem((nr, nc, 4), false);
 
 		// handle one photon in its entirety before moving onto the next
 		while let Some(Photon { mut pos, mut dir }) = photons.pop() {
 			loop {
-				if !seen_photons.insert(Photon { pos, dir }) {
+				let seen = &mut seen_photons[[pos[0], pos[1], dir.into()]];
+				if *seen {
 					break;
 				}
+				*seen = true;
+
+				let tile = self.tiles[pos];
+				let exit_pos = tile.exit_pos(pos);
 				lit_tiles[pos] = true;
-				let [ri, ci] = pos;
-				match board[pos] {
-					SplitterHorizontal if matches!(dir, N | S) => {
-						// if we end up with two photons (we aren't against the edge of the
-						// board), "this" one goes east; we save the westward bound for later.
-						// otherwise "this" one goes in the open direction
-						if ci == 0 {
-							dir = E;
-						} else if ci == nc - 1 {
-							dir = W;
-						} else {
+				lit_tiles[exit_pos] = true;
+
+				dir = match tile.exits(dir) {
+					Exits::Absorbed => break,
+					Exits::One(dir) => dir,
+					Exits::Split(dir, other_dir) => {
+						if let Some(other_pos) = self.advance(exit_pos, other_dir) {
 							photons.push(Photon {
-								pos: [ri, ci - 1],
-								dir: W,
+								pos: other_pos,
+								dir: other_dir,
 							});
-
-							dir = E;
 						}
+						dir
 					}
-					SplitterVertical if matches!(dir, E | W) => {
-						// same logic as above, except we keep the southbound photon and save
-						// the northbound for later
-						if ri == 0 {
-							dir = S;
-						} else if ri == nr - 1 {
-							dir = N;
-						} else {
-							photons.push(Photon {
-								pos: [ri - 1, ci],
-								dir: N,
-							});
+				};
+				pos = match self.advance(exit_pos, dir) {
+					Some(pos) => pos,
+					None => break,
+				};
+			}
+		}
 
-							dir = S;
-						}
+		Ok(Energized {
+			board: self,
+			lit_tiles,
+			seen_photons,
+		})
+	}
+
+	fn n_energized(&self, start_pos: [usize; 2], start_dir: Direction) -> usize {
+		self.energize(start_pos, start_dir).unwrap().n_lit()
+	}
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum RenderStyle {
+	/// `#` for energized tiles and `.` for the rest, as in the puzzle
+	Mask,
+	/// The board, with the beams drawn over its empty tiles: an arrow where one beam
+	/// passes through, or the number of beams where several do
+	Beams,
+}
+
+/// Where the beams went
+#[derive(Debug, Clone)]
+pub struct Energized<'a> {
+	board: &'a Board,
+	lit_tiles: Array2<bool>,
+	/// Indexed by `[ri, ci, dir]`; whether a photon entered `[ri, ci]` heading `dir`
+	seen_photons: Array3<bool>,
+}
+
+impl Energized<'_> {
+	/// The number of energized tiles
+	pub fn n_lit(&self) -> usize {
+		self.lit_tiles.iter().filter(|&&lit| lit).count()
+	}
+
+	/// Every photon state reached, i.e., every tile each beam passes through, along with
+	/// the direction the beam enters it in
+	pub fn beams(&self) -> impl Iterator<Item = Photon> + '_ {
+		use Direction::*;
+
+		self.seen_photons
+			.indexed_iter()
+			.filter(|&(_, &seen)| seen)
+			.map(|((ri, ci, dir_idx), _)| Photon {
+				pos: [ri, ci],
+				dir: [N, S, E, W][dir_idx],
+			})
+	}
+
+	/// Draws the board the beams went through, in `style`
+	pub fn render(&self, style: RenderStyle) -> String {
+		const ARROWS: [char; 4] = ['^', 'v', '>', '<'];
+
+		let (_, nc) = self.board.tiles.dim();
+
+		let mut rendered = String::new();
+		for ((ri, ci), &tile) in self.board.tiles.indexed_iter() {
+			rendered.push(match style {
+				RenderStyle::Mask if self.lit_tiles[[ri, ci]] => '#',
+				RenderStyle::Mask => '.',
+				RenderStyle::Beams if tile == Tile::Empty => {
+					let dirs = self.seen_photons.slice(s![ri, ci, ..]);
+					match dirs.iter().filter(|&&seen| seen).count() {
+						0 => '.',
+						1 => ARROWS[dirs.iter().position(|&seen| seen).unwrap()],
+						n => char::from_digit(n.cast(), 10).unwrap(),
 					}
-					MirrorSlash => {
-						dir = match dir {
-							N => E,
-							E => N,
-							S => W,
-							W => S,
-						}
+				}
+				RenderStyle::Beams => tile.to_char(),
+			});
+			if ci == nc - 1 {
+				rendered.push('\n');
+			}
+		}
+		rendered
+	}
+}
+
+/// A bitset with one bit per tile of the board, in row-major order
+type TileSet = Vec<u64>;
+
+fn insert(set: &mut TileSet, nc: usize, [ri, ci]: [usize; 2]) {
+	let i = ri * nc + ci;
+	set[i / 64] |= 1 << (i % 64);
+}
+
+/// The tiles lit by each pair of beams that a photon is split into, computed once and
+/// shared by every beam that gets split the same way.
+///
+/// The nodes of the beam graph are the photon states that get split (a tile, and a
+/// direction it's entered in), with an edge from each node to each node that one of its
+/// two outgoing beams is split at next. Nodes in the same strongly connected component
+/// light exactly the same tiles, and the tiles lit from a component are the tiles along
+/// its beams plus the tiles lit from the components those beams run into, so they can be
+/// filled in one component at a time, in reverse topological order.
+#[derive(Debug)]
+struct BeamGraph<'a> {
+	board: &'a Board,
+	/// The node of each photon state that gets split, indexed by `[ri, ci, dir]`
+	nodes: Array3<Option<usize>>,
+	/// The tiles lit from each node
+	lit_from: Vec<TileSet>,
+}
+
+/// The tiles a beam passes through, up to and including the tile it's split at, if any
+struct Segment {
+	tiles: Vec<[usize; 2]>,
+	/// The node the beam is split at
+	end: Option<usize>,
+}
+
+impl<'a> BeamGraph<'a> {
+	/// Follows the beam starting at `photon` until it's split, absorbed, leaves the
+	/// board, or repeats a state. `stamps` records, for each `[ri, ci, dir]`, the last
+	/// `stamp` of the beam to pass through it.
+	fn trace(&self, photon: Photon, stamps: &mut Array3<usize>, stamp: usize) -> Segment {
+		let Photon { mut pos, mut dir } = photon;
+		let mut tiles = Vec::new();
+
+		loop {
+			let seen = &mut stamps[[pos[0], pos[1], dir.into()]];
+			if *seen == stamp {
+				return Segment { tiles, end: None };
+			}
+			*seen = stamp;
+
+			let tile = self.board.tiles[pos];
+			let exit_pos = tile.exit_pos(pos);
+			tiles.extend([pos, exit_pos]);
+
+			dir = match tile.exits(dir) {
+				Exits::Absorbed => return Segment { tiles, end: None },
+				Exits::One(dir) => dir,
+				Exits::Split(..) => {
+					return Segment {
+						tiles,
+						end: self.nodes[[pos[0], pos[1], dir.into()]],
+					};
+				}
+			};
+			pos = match self.board.advance(exit_pos, dir) {
+				Some(pos) => pos,
+				None => return Segment { tiles, end: None },
+			};
+		}
+	}
+
+	fn new(board: &'a Board) -> Self {
+		use Direction::*;
+
+		let (nr, nc) = board.tiles.dim();
+		let mut split_photons = Vec::new();
+		let nodes = Array3::from_shape_fn((nr, nc, 4), |(ri, ci, dir_idx)| {
+			let dir = [N, S, E, W][dir_idx];
+			let Exits::Split(..) = board.tiles[[ri, ci]].exits(dir) else {
+				return None;
+			};
+			split_photons.push(Photon { pos: [ri, ci], dir });
+			Some(split_photons.len() - 1)
+		});
+		let mut graph = Self {
+			board,
+			nodes,
+			lit_from: Vec::new(),
+		};
+
+		// every node's two outgoing beams
+		let mut stamps = Array3::from_elem((nr, nc, 4), usize::MAX);
+		let mut segments = Vec::with_capacity(split_photons.len());
+		for &Photon { pos, dir } in &split_photons {
+			let tile = board.tiles[pos];
+			let exit_pos = tile.exit_pos(pos);
+			let Exits::Split(dir1, dir2) = tile.exits(dir) else {
+				unreachable!("nodes are split")
+			};
+			segments.push([dir1, dir2].map(|dir| {
+				let stamp = 2 * segments.len() + usize::from(dir == dir2);
+				match board.advance(exit_pos, dir) {
+					Some(pos) => graph.trace(Photon { pos, dir }, &mut stamps, stamp),
+					None => Segment {
+						tiles: Vec::new(),
+						end: None,
+					},
+				}
+			}));
+		}
+
+		let successors = segments
+			.iter()
+			.map(|segs| segs.iter().filter_map(|seg| seg.end).collect::<Vec<_>>())
+			.collect::<Vec<_>>();
+
+		let n_words = (nr * nc).div_ceil(64);
+		graph.lit_from = vec![Vec::new(); split_photons.len()];
+		for component in strongly_connected_components(&successors) {
+			let mut lit = vec![0; n_words];
+			for &node in &component {
+				let pos = split_photons[node].pos;
+				insert(&mut lit, nc, pos);
+				insert(&mut lit, nc, board.tiles[pos].exit_pos(pos));
+				for seg in &segments[node] {
+					for &pos in &seg.tiles {
+						insert(&mut lit, nc, pos);
 					}
-					MirrorBackslash => {
-						dir = match dir {
-							N => W,
-							W => N,
-							S => E,
-							E => S,
+					if let Some(next) = seg.end
+						&& !component.contains(&next)
+					{
+						for (word, next_word) in lit.iter_mut().zip(&graph.lit_from[next]) {
+							*word |= next_word;
 						}
 					}
-					_ => {}
 				}
+			}
+			for &node in &component {
+				graph.lit_from[node].clone_from(&lit);
+			}
+		}
 
-				pos = match dir {
-					N if ri > 0 => [ri - 1, ci],
-					S if ri < nr - 1 => [ri + 1, ci],
-					E if ci < nc - 1 => [ri, ci + 1],
-					W if ci > 0 => [ri, ci - 1],
-					_ => break,
-				};
+		graph
+	}
+
+	fn n_energized(&self, start_pos: [usize; 2], start_dir: Direction) -> usize {
+		let (nr, nc) = self.board.tiles.dim();
+		let mut stamps = Array3::from_elem((nr, nc, 4), usize::MAX);
+		let Segment { tiles, end } = self.trace(
+			Photon {
+				pos: start_pos,
+				dir: start_dir,
+			},
+			&mut stamps,
+			0,
+		);
+
+		let mut lit = match end {
+			Some(node) => self.lit_from[node].clone(),
+			None => vec![0; (nr * nc).div_ceil(64)],
+		};
+		for pos in tiles {
+			insert(&mut lit, nc, pos);
+		}
+		lit.iter()
+			.map(|word| word.count_ones().cast::<usize>())
+			.sum()
+	}
+}
+
+/// Tarjan's algorithm. Returns the strongly connected components of the graph with
+/// adjacency lists `successors`, each one after all the components it has edges into.
+fn strongly_connected_components(successors: &[Vec<usize>]) -> Vec<Vec<usize>> {
+	struct State<'a> {
+		successors: &'a [Vec<usize>],
+		index: Vec<Option<usize>>,
+		low_link: Vec<usize>,
+		on_stack: Vec<bool>,
+		stack: Vec<usize>,
+		n_indexed: usize,
+		components: Vec<Vec<usize>>,
+	}
+
+	fn visit(state: &mut State, node: usize) {
+		state.index[node] = Some(state.n_indexed);
+		state.low_link[node] = state.n_indexed;
+		state.n_indexed += 1;
+		state.stack.push(node);
+		state.on_stack[node] = true;
+
+		for &next in &state.successors[node] {
+			match state.index[next] {
+				None => {
+					visit(state, next);
+					state.low_link[node] = state.low_link[node].min(state.low_link[next]);
+				}
+				Some(next_index) if state.on_stack[next] => {
+					state.low_link[node] = state.low_link[node].min(next_index);
+				}
+				Some(_) => {}
+			}
+		}
+
+		if Some(state.low_link[node]) == state.index[node] {
+			let mut component = Vec::new();
+			loop {
+				let member = state.stack.pop().unwrap();
+				state.on_stack[member] = false;
+				component.push(member);
+				if member == node {
+					break;
+				}
 			}
+			state.components.push(component);
 		}
+	}
 
-		lit_tiles.into_iter().filter(|&lit| lit).count()
+	let n = successors.len();
+	let mut state = State {
+		successors,
+		index: vec![None; n],
+		low_link: vec![0; n],
+		on_stack: vec![false; n],
+		stack: Vec::new(),
+		n_indexed: 0,
+		components: Vec::new(),
+	};
+	for node in 0..n {
+		if state.index[node].is_none() {
+			visit(&mut state, node);
+		}
 	}
+	state.components
 }
 // end::setup[]
 
@@ -167,18 +605,25 @@ fn pt1(board: &Board) -> usize {
 // end::pt1[]
 
 // tag::pt2[]
-fn pt2(board: &Board) -> usize {
+/// The beam entering from the edge of the board that energizes the most tiles, and how
+/// many tiles it energizes
+fn best_entry(board: &Board) -> (Photon, usize) {
 	use Direction::*;
-	let (nr, nc) = board.0.dim();
+	let (nr, nc) = board.tiles.dim();
+	let graph = BeamGraph::new(board);
 
 	(0..nr)
 		.flat_map(|ri| [([ri, 0], E), ([ri, nc - 1], W)])
 		.chain((0..nc).flat_map(|ci| [([0, ci], S), ([nr - 1, ci], N)]))
-		.map(|(start_pos, start_dir)| board.n_energized(start_pos, start_dir))
-		.max()
+		.map(|(pos, dir)| (Photon { pos, dir }, graph.n_energized(pos, dir)))
+		.max_by_key(|&(_, n)| n)
 		.unwrap()
 }
 
+fn pt2(board: &Board) -> usize {
+	best_entry(board).1
+}
+
 // end::pt2[]
 
 #[cfg(test)]
@@ -205,4 +650,161 @@ mod test {
 			(pt2, 8489),
 		);
 	}
+
+	#[test]
+	fn render() {
+		let board = read_input(&read_file!("sample_input.txt"));
+		let energized = board.energize([0, 0], Direction::E).unwrap();
+
+		assert_eq!(
+			energized.render(RenderStyle::Mask),
+			"\
+			######....\n\
+			.#...#....\n\
+			.#...#####\n\
+			.#...##...\n\
+			.#...##...\n\
+			.#...##...\n\
+			.#..####..\n\
+			########..\n\
+			.#######..\n\
+			.#...#.#..\n"
+		);
+		assert_eq!(
+			energized.render(RenderStyle::Beams),
+			"\
+			>|<<<\\....\n\
+			|v-.\\^....\n\
+			.v...|->>>\n\
+			.v...v^.|.\n\
+			.v...v^...\n\
+			.v...v^..\\\n\
+			.v../2\\\\..\n\
+			<->-/vv|..\n\
+			.|<<<2-|.\\\n\
+			.v//.|.v..\n"
+		);
+
+		let beams = energized.beams().collect::<Vec<_>>();
+		assert!(beams.contains(&Photon {
+			pos: [0, 0],
+			dir: Direction::E
+		}));
+		let mut lit = beams.iter().map(|photon| photon.pos).collect::<Vec<_>>();
+		lit.dedup();
+		assert_eq!(lit.len(), energized.n_lit());
+
+		let (best, n) = best_entry(&board);
+		assert_eq!(
+			best,
+			Photon {
+				pos: [0, 3],
+				dir: Direction::S
+			}
+		);
+		assert_eq!(board.n_energized(best.pos, best.dir), n);
+	}
+
+	#[test]
+	fn beam_graph() {
+		use Direction::*;
+
+		for input in [read_file!("sample_input.txt"), read_file!("input.txt")] {
+			let board = read_input(&input);
+			let graph = BeamGraph::new(&board);
+			let (nr, nc) = board.tiles.dim();
+
+			// the edges, and a sample of tiles away from them, in every direction
+			for ((ri, ci), _) in board.tiles.indexed_iter() {
+				if (ri + ci) % 7 != 0 && ri != 0 && ci != 0 && ri != nr - 1 && ci != nc - 1 {
+					continue;
+				}
+				for dir in [N, S, E, W] {
+					assert_eq!(
+						graph.n_energized([ri, ci], dir),
+						board.n_energized([ri, ci], dir),
+						"{:?}",
+						([ri, ci], dir)
+					);
+				}
+			}
+		}
+
+		// beams that loop around the edge forever, passing lengthwise through the splitter
+		// they came out of
+		let board = read_input(
+			"\
+			/...\\\n\
+			.....\n\
+			|....\n\
+			.....\n\
+			\\.../\n",
+		);
+		let graph = BeamGraph::new(&board);
+		assert_eq!(graph.n_energized([2, 0], E), 16);
+		assert_eq!(board.n_energized([2, 0], E), 16);
+	}
+
+	#[test]
+	fn elements() {
+		use Direction::*;
+
+		let board = read_input("..#..\n");
+		assert_eq!(board.n_energized([0, 0], E), 3);
+
+		let board = read_input(".>.<.\n");
+		assert_eq!(board.n_energized([0, 0], E), 4);
+		assert_eq!(board.n_energized([0, 4], W), 4);
+		assert_eq!(board.n_energized([0, 2], E), 2);
+
+		let board = read_input(".A..\n....\n..A.\n");
+		assert_eq!(
+			board.energize([0, 0], E).unwrap().render(RenderStyle::Mask),
+			"##..\n....\n..##\n"
+		);
+
+		let board = read_input("...\n./.\n...\n");
+		assert_eq!(board.n_energized([1, 0], E), 3);
+		let board = board.with_edges(Edges::Wrapping);
+		assert_eq!(
+			board.energize([1, 0], E).unwrap().render(RenderStyle::Mask),
+			".#.\n###\n.#.\n"
+		);
+
+		let Err(AocError::Other(err)) = board.energize([3, 0], E) else {
+			panic!("[3, 0] isn't on the board")
+		};
+		assert_eq!(err, "[3, 0] isn't on the 3x3 board");
+
+		for bad in ["A..\n", "A.A\n..A\n", ".x.\n"] {
+			assert!(bad.parse::<Board>().is_err(), "{bad:?}");
+		}
+
+		// the beam graph handles every kind of element
+		let variant = "\
+			.|...\\..A.\n\
+			|.-.\\.....\n\
+			.....|-...\n\
+			..#.....|.\n\
+			..........\n\
+			.>.......\\\n\
+			..../.\\\\..\n\
+			.-.-/..|..\n\
+			.|..A.-|.\\\n\
+			..//.|..^.\n";
+		for edges in [Edges::Open, Edges::Wrapping] {
+			let board = read_input(variant).with_edges(edges);
+			let graph = BeamGraph::new(&board);
+			for ((ri, ci), _) in board.tiles.indexed_iter() {
+				for dir in [N, S, E, W] {
+					assert_eq!(
+						graph.n_energized([ri, ci], dir),
+						board.n_energized([ri, ci], dir),
+						"{:?}",
+						([ri, ci], dir, edges)
+					);
+				}
+			}
+		}
+	}
 }
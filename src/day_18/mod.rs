@@ -1,9 +1,13 @@
 // tag::setup[]
 use crate::{
 	error::{AocResult, ToResultDefaultErr},
-	read_file, regex, Answer, AocError,
+	polygon::Polygon,
+	read_file, regex,
+	utils::get_nsew_adjacent,
+	Answer, AocError, Cast,
 };
-use std::str::FromStr;
+use ndarray::prelude::*;
+use std::{collections::BTreeSet, str::FromStr};
 use strum_macros::EnumString;
 
 fn ans_for_input(input: &str) -> Answer<i64, i64> {
@@ -31,21 +35,40 @@ enum Direction {
 	R,
 }
 
+impl Direction {
+	fn opposite(self) -> Self {
+		use Direction::*;
+
+		match self {
+			U => D,
+			D => U,
+			L => R,
+			R => L,
+		}
+	}
+}
+
 #[derive(Debug, Clone, Copy)]
 struct CombinedInstr {
 	pt1_direction: Direction,
 	pt1_dist: i64,
 	pt2_direction: Direction,
 	pt2_dist: i64,
+	/// The hex code, read as a color
+	color: Rgb,
 }
 
+type Rgb = [u8; 3];
+
 impl FromStr for CombinedInstr {
 	type Err = AocError;
 
 	fn from_str(s: &str) -> Result<Self, Self::Err> {
-		let m = regex!(r"(?<dir>\w+)\s+(?<dist>\d+)\s+\(#(?<color_dist>\w{5})(?<color_dir>\w)\)")
-			.captures(s)
-			.to_result()?;
+		let m = regex!(
+			r"(?<dir>\w+)\s+(?<dist>\d+)\s+\(#(?<color>(?<color_dist>\w{5})(?<color_dir>\w))\)"
+		)
+		.captures(s)
+		.to_result()?;
 		let pt1_direction = m.name("dir").unwrap().as_str().parse()?;
 		let pt1_dist = m.name("dist").unwrap().as_str().parse()?;
 
@@ -58,11 +81,15 @@ impl FromStr for CombinedInstr {
 			c => return Err(AocError::Other(format!("invalid char {c:?}"))),
 		};
 
+		let [_, color @ ..] =
+			u32::from_str_radix(m.name("color").unwrap().as_str(), 16)?.to_be_bytes();
+
 		Ok(Self {
 			pt1_direction,
 			pt1_dist,
 			pt2_direction,
 			pt2_dist,
+			color,
 		})
 	}
 }
@@ -71,6 +98,7 @@ impl FromStr for CombinedInstr {
 struct Instr {
 	direction: Direction,
 	dist: i64,
+	color: Rgb,
 }
 
 impl Instr {
@@ -78,60 +106,286 @@ impl Instr {
 		CombinedInstr {
 			pt1_direction: direction,
 			pt1_dist: dist,
+			color,
 			..
 		}: CombinedInstr,
 	) -> Self {
-		Self { direction, dist }
+		Self {
+			direction,
+			dist,
+			color,
+		}
 	}
 	fn from_pt2(
 		CombinedInstr {
 			pt2_direction: direction,
 			pt2_dist: dist,
+			color,
 			..
 		}: CombinedInstr,
 	) -> Self {
-		Self { direction, dist }
+		Self {
+			direction,
+			dist,
+			color,
+		}
 	}
 }
 
-fn get_n_interior_points(instrs: &[Instr]) -> i64 {
+type Point = [i64; 2];
+
+/// An edge of the trench, dug by the instruction at `idx`
+#[derive(Debug, Clone, Copy)]
+struct Edge {
+	idx: usize,
+	start: Point,
+	end: Point,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Event {
+	/// An edge along the first axis starts
+	Insert,
+	/// An edge along the second axis is crossed
+	Query,
+	/// An edge along the first axis ends
+	Remove,
+}
+
+/// Looks for two edges that touch, other than consecutive edges (including the last and
+/// the first) at the point they share, by sweeping a line along the first axis. Edges
+/// along the first axis are kept in a set ordered by their second coordinate for as long
+/// as the sweep line crosses them, so each edge along the second axis only has to check
+/// the range of that set that it spans. Edges that lie along the second axis and overlap
+/// each other aren't found; sweeping along the second axis finds those.
+///
+/// Returns the indices of the two edges, and a point where they touch.
+fn sweep(edges: &[Edge]) -> Option<(usize, usize, Point)> {
+	let n = edges.len();
+	let consecutive = |i: usize, j: usize| (i + 1) % n == j || (j + 1) % n == i;
+
+	let mut events = Vec::with_capacity(2 * n);
+	for &Edge {
+		idx,
+		start: [x1, y1],
+		end: [x2, y2],
+	} in edges
+	{
+		if y1 == y2 {
+			events.push((x1.min(x2), Event::Insert, idx, y1, y1));
+			events.push((x1.max(x2), Event::Remove, idx, y1, y1));
+		} else {
+			events.push((x1, Event::Query, idx, y1.min(y2), y1.max(y2)));
+		}
+	}
+	events.sort_unstable();
+
+	let mut active = BTreeSet::new();
+	for (x, event, idx, lo, hi) in events {
+		match event {
+			Event::Insert | Event::Query => {
+				if let Some(&(y, other)) = active
+					.range((lo, 0)..=(hi, usize::MAX))
+					.find(|&&(_, other)| !consecutive(idx, other))
+				{
+					return Some((other.min(idx), other.max(idx), [x, y]));
+				}
+				if event == Event::Insert {
+					active.insert((lo, idx));
+				}
+			}
+			Event::Remove => {
+				active.remove(&(lo, idx));
+			}
+		}
+	}
+
+	None
+}
+
+/// The polygon through the centers of the trench's tiles, starting from `[0, 0]`. `Err`
+/// unless the instructions trace out a simple closed loop: every instruction must move
+/// somewhere, no instruction may turn straight back along the one before it, the last
+/// instruction must end where the first started, and edges may only touch the edges
+/// before and after them, where they meet.
+fn dig_polygon(instrs: &[Instr]) -> AocResult<Polygon> {
 	use Direction::*;
 
-	// Strategy: use the <https://en.wikipedia.org/wiki/Shoelace_formula> \
-	// Minor issue: we don't have the vertices defining the edges that surround a
-	// polygon; rather, we have the centers of tiles; the outsides of those tiles are
-	// what surround the polygon. Since the tiles' centers lie in the center of the
-	// squares, we undercount by 0.5 per edge tile. We also have the corners to consider;
-	// since we go around one full time, that's another full tile of area that the
-	// shoelace theorem will miss. So at the end, we add `edge_len / 2 + 1` to the
-	// naive shoelace answer of `twice_area / 2`
-
-	let p0 = [0, 0]; // arbitrary point
-	let mut pos = p0;
-	let mut twice_area = 0;
-	let mut edge_len = 0;
-
-	for &Instr {
-		direction, dist, ..
-	} in instrs
+	let n = instrs.len();
+	if n == 0 {
+		return Err(AocError::Other("empty dig plan".to_owned()));
+	}
+
+	for (i, instr) in instrs.iter().enumerate() {
+		if instr.dist <= 0 {
+			return Err(AocError::Other(format!(
+				"instruction {i} moves {} tiles",
+				instr.dist
+			)));
+		}
+		if i > 0 && instr.direction == instrs[i - 1].direction.opposite() {
+			return Err(AocError::Other(format!(
+				"instruction {i} doubles back along instruction {}",
+				i - 1
+			)));
+		}
+	}
+
+	let mut pos: Point = [0, 0];
+	let mut edges = Vec::with_capacity(n);
+	for (
+		idx,
+		&Instr {
+			direction, dist, ..
+		},
+	) in instrs.iter().enumerate()
 	{
-		let [x1, y1] = pos;
+		let [x, y] = pos;
+		let end = match direction {
+			U => y.checked_sub(dist).map(|y| [x, y]),
+			D => y.checked_add(dist).map(|y| [x, y]),
+			L => x.checked_sub(dist).map(|x| [x, y]),
+			R => x.checked_add(dist).map(|x| [x, y]),
+		}
+		.ok_or_else(|| {
+			AocError::Other(format!(
+				"instruction {idx} digs from {pos:?} past the edge of the i64 grid"
+			))
+		})?;
+		edges.push(Edge {
+			idx,
+			start: pos,
+			end,
+		});
+		pos = end;
+	}
+
+	if pos != [0, 0] {
+		// widened, since `-i64::MIN` doesn't fit in an `i64`
+		let [x, y] = pos.map(i128::from);
+		return Err(AocError::Other(format!(
+			"instruction {} ends at {pos:?}, leaving a gap of {:?} back to the start",
+			n - 1,
+			[-x, -y]
+		)));
+	}
+	if instrs[0].direction == instrs[n - 1].direction.opposite() {
+		return Err(AocError::Other(format!(
+			"instruction 0 doubles back along instruction {}",
+			n - 1
+		)));
+	}
+
+	let transpose = |[x, y]: Point| [y, x];
+	let transposed = edges
+		.iter()
+		.map(|&Edge { idx, start, end }| Edge {
+			idx,
+			start: transpose(start),
+			end: transpose(end),
+		})
+		.collect::<Vec<_>>();
+	let touching =
+		sweep(&edges).or_else(|| sweep(&transposed).map(|(i, j, point)| (i, j, transpose(point))));
+	if let Some((i, j, point)) = touching {
+		return Err(AocError::Other(format!(
+			"instructions {i} and {j} touch at {point:?}"
+		)));
+	}
+
+	Ok(Polygon::new(edges.iter().map(|edge| edge.start).collect()))
+}
+
+/// The most pixels [`to_ppm`] will draw
+const MAX_PIXELS: i64 = 1 << 24;
+const INTERIOR_COLOR: Rgb = [0x3f, 0x3f, 0x3f];
+const GROUND_COLOR: Rgb = [0xff, 0xff, 0xff];
+
+/// Draws the lagoon as a binary PPM image (<https://netpbm.sourceforge.net/doc/ppm.html>),
+/// one pixel per tile: the trench in the colors of the instructions that dug it, the
+/// interior in [`INTERIOR_COLOR`], and the ground around it in [`GROUND_COLOR`]. `Err`
+/// if the dig plan isn't valid (see [`dig_polygon`]), or would take more than
+/// [`MAX_PIXELS`] pixels.
+fn to_ppm(instrs: &[Instr]) -> AocResult<Vec<u8>> {
+	let polygon = dig_polygon(instrs)?;
+	let vertices = polygon.vertices();
 
-		let [x2, y2] = match direction {
-			U => [x1, y1 - dist],
-			D => [x1, y1 + dist],
-			L => [x1 - dist, y1],
-			R => [x1 + dist, y1],
+	let (xs, ys): (Vec<_>, Vec<_>) = vertices.iter().map(|&[x, y]| (x, y)).unzip();
+	let [x_min, y_min] = [xs.iter().min().unwrap(), ys.iter().min().unwrap()];
+	let [x_max, y_max] = [xs.iter().max().unwrap(), ys.iter().max().unwrap()];
+	// the sides can be too long for an `i64` themselves, let alone their product
+	let width = x_max.checked_sub(*x_min).and_then(|dx| dx.checked_add(1));
+	let height = y_max.checked_sub(*y_min).and_then(|dy| dy.checked_add(1));
+	let (Some(width), Some(height)) = (width, height) else {
+		return Err(AocError::Other(format!(
+			"a lagoon from {:?} to {:?} is too big to draw",
+			[x_min, y_min],
+			[x_max, y_max]
+		)));
+	};
+	if width
+		.checked_mul(height)
+		.is_none_or(|n_pixels| n_pixels > MAX_PIXELS)
+	{
+		return Err(AocError::Other(format!(
+			"a {width}x{height} image is too big to draw"
+		)));
+	}
+
+	// `None` until we know what's there
+	let mut pixels = Array2::from_elem((height.cast(), width.cast()), None);
+	for (&[x, y], instr) in vertices.iter().zip(instrs) {
+		let [dx, dy] = match instr.direction {
+			Direction::U => [0, -1],
+			Direction::D => [0, 1],
+			Direction::L => [-1, 0],
+			Direction::R => [1, 0],
 		};
+		for step in 0..instr.dist {
+			let [ri, ci] = [y + step * dy - y_min, x + step * dx - x_min];
+			pixels[[ri.cast::<usize>(), ci.cast::<usize>()]] = Some(instr.color);
+		}
+	}
 
-		// shoelace theorem: we add det([[x1, x2], [y1, y2]])
-		twice_area += x1 * y2 - x2 * y1;
-		edge_len += dist;
+	// everything reachable from the edge of the image without crossing the trench is
+	// ground, and whatever's left is the interior
+	let (nr, nc) = pixels.dim();
+	let mut to_visit = (0..nr)
+		.flat_map(|ri| [[ri, 0], [ri, nc - 1]])
+		.chain((0..nc).flat_map(|ci| [[0, ci], [nr - 1, ci]]))
+		.collect::<Vec<_>>();
+	while let Some([ri, ci]) = to_visit.pop() {
+		if pixels[[ri, ci]].is_some() {
+			continue;
+		}
+		pixels[[ri, ci]] = Some(GROUND_COLOR);
+		to_visit.extend(get_nsew_adjacent((ri, ci), 0..nr, 0..nc).map(|(ri, ci)| [ri, ci]));
+	}
 
-		pos = [x2, y2];
+	let mut ppm = format!("P6\n{width} {height}\n255\n").into_bytes();
+	for pixel in pixels {
+		ppm.extend(pixel.unwrap_or(INTERIOR_COLOR));
 	}
+	Ok(ppm)
+}
 
-	twice_area.abs() / 2 + edge_len / 2 + 1
+/// Draws the lagoon dug by the dig plan `input`, read as in part one (part two's lagoons
+/// are far too big to draw), as a PPM image; see [`to_ppm`]
+/// ## Errors
+/// If `input` isn't a valid dig plan, or the lagoon is too big to draw
+pub fn lagoon_ppm(input: &str) -> AocResult<Vec<u8>> {
+	let instrs = input
+		.lines()
+		.map(|line| Ok(Instr::from_pt1(line.parse()?)))
+		.collect::<AocResult<Vec<_>>>()?;
+	to_ppm(&instrs)
+}
+
+fn get_n_interior_points(instrs: &[Instr]) -> i64 {
+	// The lagoon is every tile whose center is on or inside the polygon through the
+	// centers of the trench's tiles
+	let polygon = dig_polygon(instrs).unwrap();
+	(polygon.n_interior_points().unwrap() + polygon.n_boundary_points()).cast()
 }
 // end::setup[]
 
@@ -181,4 +435,169 @@ mod test {
 			(pt2, 92_291_468_914_147),
 		);
 	}
+
+	#[test]
+	fn polygon() {
+		use crate::polygon::{Location, Orientation};
+
+		let instrs = read_input(&read_file!("sample_input.txt"))
+			.into_iter()
+			.map(Instr::from_pt1)
+			.collect::<Vec<_>>();
+		let polygon = dig_polygon(&instrs).unwrap();
+		assert_eq!(polygon.n_boundary_points(), 38);
+		assert_eq!(polygon.orientation(), Some(Orientation::Positive));
+
+		// the lagoon is every point not outside the polygon
+		let (xs, ys): (Vec<_>, Vec<_>) = polygon.vertices().iter().map(|&[x, y]| (x, y)).unzip();
+		let n_dug = (xs.iter().min().unwrap() - 1..=xs.iter().max().unwrap() + 1)
+			.flat_map(|x| {
+				(ys.iter().min().unwrap() - 1..=ys.iter().max().unwrap() + 1).map(move |y| [x, y])
+			})
+			.filter(|&p| polygon.locate(p) != Location::Outside)
+			.count();
+		assert_eq!(n_dug, 62);
+	}
+
+	#[test]
+	fn validation() {
+		fn plan(s: &str) -> Vec<Instr> {
+			s.split(',')
+				.map(|instr| {
+					let (direction, dist) = instr.trim().split_once(' ').unwrap();
+					Instr {
+						direction: direction.parse().unwrap(),
+						dist: dist.parse().unwrap(),
+						color: [0; 3],
+					}
+				})
+				.collect()
+		}
+
+		assert!(dig_polygon(&plan("R 2, D 2, L 2, U 2")).is_ok());
+		assert!(dig_polygon(&plan("R 1, R 1, D 2, L 2, U 1, U 1")).is_ok());
+
+		for (instrs, expected_err) in [
+			("R 2, D 0, L 2", "instruction 1 moves 0 tiles"),
+			(
+				"R 2, L 1, D 1",
+				"instruction 1 doubles back along instruction 0",
+			),
+			(
+				"R 2, D 2, L 2",
+				"instruction 2 ends at [0, 2], leaving a gap of [0, -2] back to the start",
+			),
+			(
+				"R 1, D 2, R 1, U 2, L 2",
+				"instruction 0 doubles back along instruction 4",
+			),
+			// a figure eight
+			(
+				"R 2, D 2, L 1, U 3, L 1, D 1",
+				"instructions 0 and 3 touch at [1, 0]",
+			),
+			// two loops that share a corner
+			(
+				"R 1, D 1, R 1, D 1, L 1, U 1, L 1, U 1",
+				"instructions 2 and 6 touch at [1, 1]",
+			),
+			// an edge lying along another
+			(
+				"R 3, D 2, L 1, U 2, R 1, U 1, L 3, D 1",
+				"instructions 0 and 4 touch at [2, 0]",
+			),
+			// off the edge of the grid, either way
+			(
+				"R 9223372036854775807, D 1, R 1",
+				"instruction 2 digs from [9223372036854775807, 1] past the edge of the i64 grid",
+			),
+			(
+				"U 9223372036854775807, L 1, U 2",
+				"instruction 2 digs from [-1, -9223372036854775807] past the edge of the i64 grid",
+			),
+			(
+				"U 9223372036854775807, L 1, U 1",
+				"instruction 2 ends at [-1, -9223372036854775808], leaving a gap of \
+				 [1, 9223372036854775808] back to the start",
+			),
+		] {
+			let Err(AocError::Other(err)) = dig_polygon(&plan(instrs)) else {
+				panic!("expected an error for {instrs:?}");
+			};
+			assert_eq!(err, expected_err, "{instrs:?}");
+		}
+	}
+
+	#[test]
+	fn ppm() {
+		use Direction::*;
+
+		let ppm = lagoon_ppm(&read_file!("sample_input.txt")).unwrap();
+
+		let header = b"P6\n7 10\n255\n";
+		assert_eq!(&ppm[..header.len()], header);
+		let pixels = ppm[header.len()..].chunks(3).collect::<Vec<_>>();
+		assert_eq!(pixels.len(), 7 * 10);
+
+		// the first instruction, `R 6 (#70c710)`, starts in the top left corner
+		assert_eq!(pixels[0], [0x70, 0xc7, 0x10]);
+		assert_eq!(
+			pixels
+				.iter()
+				.filter(|&&pixel| pixel == INTERIOR_COLOR)
+				.count(),
+			62 - 38
+		);
+		assert_eq!(
+			pixels
+				.iter()
+				.filter(|&&pixel| pixel == GROUND_COLOR)
+				.count(),
+			7 * 10 - 62
+		);
+
+		let instrs = read_input(&read_file!("sample_input.txt"))
+			.into_iter()
+			.map(Instr::from_pt2)
+			.collect::<Vec<_>>();
+		let Err(AocError::Other(err)) = to_ppm(&instrs) else {
+			panic!("part two's lagoon is too big to draw")
+		};
+		assert_eq!(err, "a 1186329x1186329 image is too big to draw");
+
+		// sides whose product is too big for an `i64`
+		let side = 1 << 33;
+		let square = [R, D, L, U]
+			.map(|direction| Instr {
+				direction,
+				dist: side,
+				color: [0; 3],
+			})
+			.to_vec();
+		let Err(AocError::Other(err)) = to_ppm(&square) else {
+			panic!("a lagoon 2³³ + 1 tiles square is too big to draw")
+		};
+		assert_eq!(err, "a 8589934593x8589934593 image is too big to draw");
+
+		// or that are themselves
+		let instrs = [L, D, R, R, U, L]
+			.into_iter()
+			.zip([i64::MAX, 1, i64::MAX, i64::MAX, 1, i64::MAX])
+			.map(|(direction, dist)| Instr {
+				direction,
+				dist,
+				color: [0; 3],
+			})
+			.collect::<Vec<_>>();
+		let Err(AocError::Other(err)) = to_ppm(&instrs) else {
+			panic!("a lagoon 2⁶⁴ - 1 tiles wide is too big to draw")
+		};
+		assert_eq!(
+			err,
+			"a lagoon from [-9223372036854775807, 0] to [9223372036854775807, 1] is too big \
+			 to draw"
+		);
+
+		assert!(lagoon_ppm("R 6 (#70c710)\nX 1 (#0dc571)").is_err());
+	}
 }
@@ -1,29 +1,34 @@
 // tag::setup[]
 use crate::{
-	enum_map::EnumMap,
 	error::{AocResult, ToResultDefaultErr},
+	hyperrect::HyperRect,
 	read_file, regex, Answer, AocError,
 };
 use indexmap::IndexMap;
-use std::{ops::ControlFlow, str::FromStr};
-use strum::EnumCount;
-use strum_macros::{EnumCount, EnumIter, EnumString};
+use std::{collections::HashSet, ops::ControlFlow, str::FromStr};
+use strum_macros::{Display, EnumString};
+use tree::compile;
+
+mod query;
+mod tree;
 
 fn ans_for_input(input: &str) -> Answer<i64, i64> {
-	let (rules, inputs) = read_input(input).unwrap();
-	(19, (pt1((&*inputs, &rules)), pt2((&*inputs, &rules)))).into()
+	let (workflows, parts) = read_input(input).unwrap();
+	(19, (pt1((&*parts, &workflows)), pt2((&*parts, &workflows)))).into()
 }
 
 pub fn ans() -> Answer<i64, i64> {
 	ans_for_input(&read_file!("input.txt"))
 }
 
-fn read_input(input: &str) -> AocResult<(IndexMap<String, RuleSet>, Vec<AttrMap>)> {
-	let mut rules = IndexMap::new();
-	let mut inputs = Vec::new();
+fn read_input(input: &str) -> AocResult<(Workflows, Vec<Part>)> {
+	let mut attrs = Attrs::default();
+	let mut workflows = Vec::new();
+	let mut problems = Vec::new();
+	let mut part_values = Vec::new();
 
-	let mut lines = input.lines();
-	for line in lines.by_ref() {
+	let mut lines = input.lines().zip(1..);
+	for (line, line_no) in lines.by_ref() {
 		if line.trim().is_empty() {
 			break;
 		}
@@ -35,71 +40,236 @@ fn read_input(input: &str) -> AocResult<(IndexMap<String, RuleSet>, Vec<AttrMap>
 		let name = caps.name("name").to_result()?.as_str().to_owned();
 		let rules_str = caps.name("rules_str").to_result()?.as_str();
 
-		let mut curr_rules = Vec::new();
-		let mut final_rule = None;
-
-		for rule_m in regex!("[^,]+").find_iter(rules_str) {
-			match rule_m.as_str().parse() {
-				Ok(rule) => curr_rules.push(rule),
-				Err(_) => final_rule = Some(rule_m.as_str().parse()?),
+		// every item but the last is a rule, and the last is just where to send parts that
+		// no rule applies to
+		let items = rules_str.split(',').collect::<Vec<_>>();
+		let (&otherwise, rule_strs) = items.split_last().to_result()?;
+		let mut rules = Vec::new();
+		for rule in rule_strs {
+			match Rule::parse(rule, &mut attrs) {
+				Ok(rule) => rules.push(rule),
+				Err(AocError::Other(msg)) => problems.push(format!("line {line_no}: {msg}")),
+				Err(err) => return Err(err),
 			}
 		}
+		let otherwise = if regex!(r"^\w+$").is_match(otherwise) {
+			otherwise.parse()?
+		} else {
+			problems.push(format!(
+				"line {line_no}: workflow {name:?} must end with a workflow, A or R, got \
+				 {otherwise:?}"
+			));
+			// stands in for the missing target so the rest of the workflow can still be
+			// checked
+			Action(ControlFlow::Break(Outcome::Reject))
+		};
 
-		assert!(rules
-			.insert(
-				name,
-				RuleSet {
-					rules: curr_rules,
-					otherwise: final_rule.unwrap(),
-				},
-			)
-			.is_none());
+		workflows.push((
+			name,
+			RuleSet {
+				rules,
+				otherwise,
+				line: line_no,
+			},
+		));
 	}
 
-	for line in lines {
-		let mut attrs = AttrMap::default();
-		for caps in regex!(r"(?<attr>\w+)=(?<value>\d+)").captures_iter(line) {
+	for (line, line_no) in lines {
+		let mut values = Vec::new();
+		for caps in regex!(r"(?<attr>\w+)=(?<value>-?\d+)").captures_iter(line) {
 			let [attr, value] = ["attr", "value"]
 				.try_map(|name| AocResult::Ok(caps.name(name).to_result()?.as_str()))?;
-			let attr = attr.parse::<Attr>()?;
-			let value = value.parse()?;
-			attrs[attr] = value;
+			values.push((attrs.intern(attr), value.parse()?));
 		}
 
-		inputs.push(attrs);
+		part_values.push((values, line_no));
 	}
 
-	Ok((rules, inputs))
+	// attributes can be first mentioned by a later part, so we can only lay parts out
+	// once they've all been read
+	let parts = part_values
+		.into_iter()
+		.map(|(values, line_no)| {
+			let mut part = vec![None; attrs.len()];
+			for (attr, value) in values {
+				if part[attr].replace(value).is_some() {
+					return Err(AocError::Other(format!(
+						"line {line_no}: part has more than one value for attribute {:?}",
+						attrs.name(attr)
+					)));
+				}
+			}
+			part.into_iter()
+				.enumerate()
+				.map(|(attr, value)| {
+					value.ok_or_else(|| {
+						AocError::Other(format!(
+							"line {line_no}: part has no value for attribute {:?}",
+							attrs.name(attr)
+						))
+					})
+				})
+				.collect()
+		})
+		.collect::<AocResult<_>>()?;
+
+	Ok((
+		Workflows {
+			rules: validate(workflows, problems)?,
+			attrs,
+		},
+		parts,
+	))
 }
 
-type AttrMap = EnumMap<{ Attr::COUNT }, Attr, i64>;
+/// Collects the workflows into a map, checking that there's an `in` workflow, that no two
+/// workflows share a name, and that every workflow sent to exists and doesn't lead back
+/// to itself. Every problem found is reported, one per line of the error, after the
+/// `problems` found while reading the workflows in (malformed rules), with each undefined
+/// workflow reported once per workflow that sends parts to it.
+fn validate(
+	workflows: Vec<(String, RuleSet)>,
+	mut problems: Vec<String>,
+) -> AocResult<IndexMap<String, RuleSet>> {
+	let mut rules = IndexMap::<String, RuleSet>::new();
+	for (name, rule_set) in workflows {
+		if let Some(first) = rules.get(&name) {
+			problems.push(format!(
+				"line {}: workflow {name:?} was already defined on line {}",
+				rule_set.line, first.line
+			));
+		} else {
+			rules.insert(name, rule_set);
+		}
+	}
+
+	if !rules.contains_key("in") {
+		problems.push("no workflow named \"in\"".to_owned());
+	}
 
-#[derive(Debug, Clone, Copy, EnumCount, EnumIter, EnumString)]
-#[strum(ascii_case_insensitive)]
-#[repr(u8)]
-enum Attr {
-	X,
-	M,
-	A,
-	S,
+	for (name, rule_set) in &rules {
+		let mut reported = HashSet::new();
+		for target in rule_set.targets() {
+			if !rules.contains_key(target) && reported.insert(target) {
+				problems.push(format!(
+					"line {}: workflow {name:?} sends parts to undefined workflow {target:?}",
+					rule_set.line
+				));
+			}
+		}
+	}
+
+	let mut finished = HashSet::new();
+	for name in rules.keys() {
+		find_cycles(&rules, name, &mut Vec::new(), &mut finished, &mut problems);
+	}
+
+	if problems.is_empty() {
+		Ok(rules)
+	} else {
+		Err(AocError::Other(problems.join("\n")))
+	}
+}
+
+/// Depth-first search from `name`, reporting each workflow in `path` that we find our way
+/// back to. Workflows in `finished` have had everything after them searched already.
+fn find_cycles<'a>(
+	rules: &'a IndexMap<String, RuleSet>,
+	name: &'a str,
+	path: &mut Vec<&'a str>,
+	finished: &mut HashSet<&'a str>,
+	problems: &mut Vec<String>,
+) {
+	if finished.contains(name) {
+		return;
+	}
+	if let Some(start) = path.iter().position(|&n| n == name) {
+		let cycle = path[start..]
+			.iter()
+			.chain([&name])
+			.map(|&n| format!("{n} (line {})", rules[n].line))
+			.collect::<Vec<_>>();
+		problems.push(format!("workflows form a cycle: {}", cycle.join(" -> ")));
+		return;
+	}
+	let Some(rule_set) = rules.get(name) else {
+		return;
+	};
+
+	path.push(name);
+	for target in rule_set.targets() {
+		find_cycles(rules, target, path, finished, problems);
+	}
+	path.pop();
+	finished.insert(name);
 }
 
-impl From<Attr> for usize {
-	fn from(attr: Attr) -> Self {
-		attr as _
+/// The bounds of each attribute unless [`Workflows::set_bounds`] says otherwise
+const DEFAULT_BOUNDS: (i64, i64) = (1, 4000);
+
+/// An index into [`Attrs`]
+type Attr = usize;
+
+/// A part's value for each attribute, indexed by [`Attr`]
+type Part = Vec<i64>;
+
+/// Every attribute mentioned in the input, in the order they were first mentioned, with
+/// the (inclusive) range of values a part can have for it
+#[derive(Debug, Clone, Default)]
+struct Attrs(IndexMap<String, (i64, i64)>);
+
+impl Attrs {
+	fn intern(&mut self, name: &str) -> Attr {
+		match self.0.get_index_of(name) {
+			Some(attr) => attr,
+			None => self.0.insert_full(name.to_owned(), DEFAULT_BOUNDS).0,
+		}
+	}
+
+	fn len(&self) -> usize {
+		self.0.len()
+	}
+
+	fn name(&self, attr: Attr) -> &str {
+		self.0.get_index(attr).unwrap().0
+	}
+
+	fn set_bounds(&mut self, name: &str, (low, high): (i64, i64)) -> AocResult<()> {
+		if Criteria::new(vec![(low, high)]).is_none() {
+			return Err(AocError::Other(format!(
+				"attribute {name:?} can't range from {low} to {high}"
+			)));
+		}
+		let bounds = self
+			.0
+			.get_mut(name)
+			.ok_or_else(|| AocError::Other(format!("no attribute named {name:?}")))?;
+		*bounds = (low, high);
+		Ok(())
+	}
+
+	/// Every part that could exist
+	fn criteria(&self) -> Criteria {
+		Criteria::new(self.0.values().copied().collect()).unwrap()
 	}
 }
 
-#[derive(Debug, Clone, Copy, EnumString)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, EnumString)]
 enum Comparison {
 	#[strum(serialize = "<")]
 	Lt,
+	#[strum(serialize = "<=")]
+	Le,
 	#[strum(serialize = ">")]
 	Gt,
+	#[strum(serialize = ">=")]
+	Ge,
+	#[strum(serialize = "==")]
+	Eq,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum Outcome {
+pub enum Outcome {
 	Accept,
 	Reject,
 }
@@ -121,33 +291,62 @@ impl FromStr for Action {
 	}
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 struct Condition {
 	attr: Attr,
 	cmp: Comparison,
 	value: i64,
 }
 
+impl Condition {
+	/// The (inclusive) range of values of `attr` for which the condition holds, or `None`
+	/// if there are none, as with `x<-9223372036854775808`
+	fn range(self) -> Option<(i64, i64)> {
+		let Self { cmp, value, .. } = self;
+		Some(match cmp {
+			Comparison::Lt => (i64::MIN, value.checked_sub(1)?),
+			Comparison::Le => (i64::MIN, value),
+			Comparison::Gt => (value.checked_add(1)?, i64::MAX),
+			Comparison::Ge => (value, i64::MAX),
+			Comparison::Eq => (value, value),
+		})
+	}
+
+	/// The parts in `criteria` for which the condition holds, if there are any, and the
+	/// rest, as up to two boxes (see [`HyperRect::split`])
+	fn split(self, criteria: Criteria) -> (Option<Criteria>, Vec<Criteria>) {
+		match self.range() {
+			Some(range) => criteria.split(self.attr, range),
+			None => (None, vec![criteria]),
+		}
+	}
+}
+
 #[derive(Debug)]
 struct Rule {
 	condition: Condition,
 	action: Action,
 }
 
-impl FromStr for Rule {
-	type Err = AocError;
-
-	fn from_str(s: &str) -> Result<Self, Self::Err> {
-		let caps = regex!(r"(?<attr>\w+)(?<cmp>>|<)(?<value>\d+):(?<action>\w+)")
+impl Rule {
+	/// Parses a rule like `x<5:A`; the error says what's wrong with it, but not where
+	fn parse(s: &str, attrs: &mut Attrs) -> AocResult<Self> {
+		let caps = regex!(r"^(?<attr>\w+)(?<cmp><=|>=|==|<|>)(?<value>-?\d+):(?<action>\w+)$")
 			.captures(s)
-			.to_result()?;
+			.ok_or_else(|| {
+				AocError::Other(format!(
+					"expected a rule like \"x<5:A\" before the last comma, got {s:?}"
+				))
+			})?;
 		let [attr, cmp, value, action] = ["attr", "cmp", "value", "action"]
 			.try_map(|name| AocResult::Ok(caps.name(name).to_result()?.as_str()))?;
 
-		let attr = attr.parse()?;
 		let cmp = cmp.parse()?;
-		let value = value.parse()?;
+		let value = value.parse().map_err(|_| {
+			AocError::Other(format!("value {value} in rule {s:?} doesn't fit in an i64"))
+		})?;
 		let action = action.parse()?;
+		let attr = attrs.intern(attr);
 
 		Ok(Self {
 			condition: Condition { attr, cmp, value },
@@ -160,143 +359,118 @@ impl FromStr for Rule {
 struct RuleSet {
 	rules: Vec<Rule>,
 	otherwise: Action,
+	/// Where the workflow was defined in the input, counting from 1
+	line: usize,
+}
+
+impl RuleSet {
+	/// The names of the workflows this one sends parts to, in order, with repeats
+	fn targets(&self) -> impl Iterator<Item = &str> {
+		self.rules
+			.iter()
+			.map(|rule| &rule.action)
+			.chain([&self.otherwise])
+			.filter_map(|Action(action)| match action {
+				ControlFlow::Continue(name) => Some(name.as_str()),
+				ControlFlow::Break(_) => None,
+			})
+	}
+}
+
+/// Every workflow, by name, and every attribute mentioned by them or by the parts
+#[derive(Debug)]
+pub struct Workflows {
+	rules: IndexMap<String, RuleSet>,
+	attrs: Attrs,
+}
+
+impl FromStr for Workflows {
+	type Err = AocError;
+
+	/// The workflows of a puzzle input, which may leave out the parts
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		read_input(s).map(|(workflows, _)| workflows)
+	}
+}
+
+impl Workflows {
+	/// Limits the parts considered to those whose value for attribute `name` is within
+	/// the (inclusive) range `bounds`, instead of `1..=4000`
+	/// ## Errors
+	/// If there's no attribute `name`, or the range is empty or ends at `i64::MAX`
+	pub fn set_bounds(&mut self, name: &str, bounds: (i64, i64)) -> AocResult<()> {
+		self.attrs.set_bounds(name, bounds)
+	}
 }
 
 // end::setup[]
 
 // tag::pt1[]
-impl Rule {
-	fn applied_to(&self, attrs: AttrMap) -> Option<&Action> {
-		let Self { condition, action } = self;
-		let &Condition { attr, cmp, value } = condition;
-
-		let x = attrs[attr];
-		let applies = match cmp {
-			Comparison::Lt => x < value,
-			Comparison::Gt => x > value,
-		};
-		applies.then_some(action)
+impl Condition {
+	fn holds_for(self, part: &[i64]) -> bool {
+		self.range()
+			.is_some_and(|(low, high)| (low..=high).contains(&part[self.attr]))
 	}
 }
 
-fn pt1((inputs, rules): (&[AttrMap], &IndexMap<String, RuleSet>)) -> i64 {
-	let init_rule_name = "in";
+fn pt1((parts, workflows): (&[Part], &Workflows)) -> i64 {
+	let tree = compile(workflows).tree;
 
-	inputs
+	parts
 		.iter()
-		.map(|&inp| {
-			let mut rule_name = init_rule_name;
-
-			loop {
-				let rule_set = &rules[rule_name];
-
-				let Action(action) = rule_set
-					.rules
-					.iter()
-					.find_map(|rule| rule.applied_to(inp))
-					.unwrap_or(&rule_set.otherwise);
-
-				match action {
-					ControlFlow::Continue(name) => rule_name = &name,
-					ControlFlow::Break(outcome) => {
-						return match outcome {
-							Outcome::Accept => inp.into_array().into_iter().sum(),
-							Outcome::Reject => 0,
-						};
-					}
-				}
-			}
+		.map(|part| match tree.evaluate(part) {
+			Outcome::Accept => part.iter().sum(),
+			Outcome::Reject => 0,
 		})
 		.sum()
 }
 // end::pt1[]
 
 // tag::pt2[]
-#[derive(Debug, Clone, Copy)]
-struct ConditionEq {
-	condition: Condition,
-	eq_allowed: bool,
-}
-
-impl std::ops::Not for ConditionEq {
-	type Output = Self;
-
-	fn not(self) -> Self::Output {
-		use Comparison::*;
-
-		let Self {
-			condition: Condition { attr, cmp, value },
-			eq_allowed,
-		} = self;
-		Self {
-			condition: Condition {
-				attr,
-				cmp: match cmp {
-					Lt => Gt,
-					Gt => Lt,
-				},
-				value,
-			},
-			eq_allowed: !eq_allowed,
-		}
-	}
-}
-
-#[derive(Debug, Clone, Copy)]
-struct Criteria(EnumMap<{ Attr::COUNT }, Attr, (i64, i64)>);
+/// A hyperrectangle of parts, with a range of values along each attribute's axis
+type Criteria = HyperRect;
 
 #[derive(Debug, Clone)]
 struct Traversal<'a> {
 	arrive_at: &'a str,
 	with_criteria: Criteria,
+	/// The workflows passed through before `arrive_at`
+	via: Vec<&'a str>,
 }
 
-impl Criteria {
-	fn add_condition(&mut self, condition: ConditionEq) {
-		let ConditionEq {
-			condition: Condition { attr, cmp, value },
-			eq_allowed,
-		} = condition;
-		let (low, high) = &mut self.0[attr];
-		match (cmp, eq_allowed) {
-			(Comparison::Lt, true) => *high = value.min(*high),
-			(Comparison::Lt, false) => *high = (value - 1).min(*high),
-			(Comparison::Gt, true) => *low = value.max(*low),
-			(Comparison::Gt, false) => *low = (value + 1).max(*low),
-		}
-	}
-
-	fn validity(self) -> Result<(), ()> {
-		if self
-			.0
-			.into_array()
-			.into_iter()
-			.all(|(low, high)| low <= high)
-		{
-			Ok(())
-		} else {
-			Err(())
-		}
-	}
+/// Parts that all take the same way through the workflows
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Region<'a> {
+	criteria: Criteria,
+	/// The workflows these parts pass through, starting with `in`
+	path: Vec<&'a str>,
+	outcome: Outcome,
 }
 
 fn handle_action<'a>(
 	paths: &mut Vec<Traversal<'a>>,
-	initial_conditions: &mut Vec<Criteria>,
+	regions: &mut Vec<Region<'a>>,
 	Action(action): &'a Action,
 	criteria: Criteria,
+	via: &[&'a str],
 ) {
 	match action {
 		ControlFlow::Continue(name) => paths.push(Traversal {
 			arrive_at: name,
 			with_criteria: criteria,
+			via: via.to_vec(),
+		}),
+		&ControlFlow::Break(outcome) => regions.push(Region {
+			criteria,
+			path: via.to_vec(),
+			outcome,
 		}),
-		ControlFlow::Break(Outcome::Accept) => initial_conditions.push(criteria),
-		ControlFlow::Break(Outcome::Reject) => {}
 	}
 }
 
-fn pt2((_, rules): (&[AttrMap], &IndexMap<String, RuleSet>)) -> i64 {
+/// Every part, divided up by the way it takes through the workflows. The regions are
+/// disjoint, and together they cover every part within the attributes' bounds.
+fn regions(workflows: &Workflows) -> Vec<Region<'_>> {
 	// Strategy: start at "in" and just traverse, taking every bifurcation, looking for
 	// the Accept states. The nice thing about this, as opposed to working backwards from
 	// the Accept states to find our way to "in", is that the ranges are automatically
@@ -305,54 +479,64 @@ fn pt2((_, rules): (&[AttrMap], &IndexMap<String, RuleSet>)) -> i64 {
 
 	let mut paths = vec![Traversal {
 		arrive_at: "in",
-		with_criteria: Criteria(EnumMap::new([(1, 4000); 4])),
+		with_criteria: workflows.attrs.criteria(),
+		via: Vec::new(),
 	}];
-	let mut initial_conditions = Vec::new();
+	let mut regions = Vec::new();
 
 	while let Some(path) = paths.pop() {
 		let Traversal {
 			arrive_at,
-			with_criteria: mut criteria,
+			with_criteria,
+			mut via,
 		} = path;
+		via.push(arrive_at);
 
-		if criteria.validity().is_err() {
-			continue;
-		}
-
-		let rule_set = &rules[arrive_at];
+		let rule_set = &workflows.rules[arrive_at];
+		// the parts that none of the rules so far have applied to
+		let mut remaining = vec![with_criteria];
 		for Rule { condition, action } in &rule_set.rules {
-			let trigger_condition = ConditionEq {
-				condition: *condition,
-				eq_allowed: false,
-			};
-
-			let mut yes_criteria = criteria;
-			yes_criteria.add_condition(trigger_condition);
-
-			handle_action(&mut paths, &mut initial_conditions, action, yes_criteria);
-
-			criteria.add_condition(!trigger_condition);
+			remaining = remaining
+				.into_iter()
+				.flat_map(|criteria| {
+					let (holds, fails) = condition.split(criteria);
+					if let Some(holds) = holds {
+						handle_action(&mut paths, &mut regions, action, holds, &via);
+					}
+					fails
+				})
+				.collect();
 		}
 
-		handle_action(
-			&mut paths,
-			&mut initial_conditions,
-			&rule_set.otherwise,
-			criteria,
-		);
+		for criteria in remaining {
+			handle_action(
+				&mut paths,
+				&mut regions,
+				&rule_set.otherwise,
+				criteria,
+				&via,
+			);
+		}
 	}
 
-	initial_conditions
-		.into_iter()
-		.map(|ranges| {
-			ranges
-				.0
-				.into_array()
-				.into_iter()
-				.map(|(low, high)| (high - low + 1).max(0))
-				.product::<i64>()
-		})
-		.sum()
+	regions
+}
+
+/// The number of parts in `regions`, which must be disjoint, or `None` if that's too
+/// many for an `i64`
+fn total_volume<'r, 'a: 'r>(regions: impl IntoIterator<Item = &'r Region<'a>>) -> Option<i64> {
+	regions.into_iter().try_fold(0_i64, |total, region| {
+		total.checked_add(region.criteria.volume()?)
+	})
+}
+
+fn pt2((_, workflows): (&[Part], &Workflows)) -> i64 {
+	total_volume(
+		regions(workflows)
+			.iter()
+			.filter(|region| region.outcome == Outcome::Accept),
+	)
+	.unwrap()
 }
 // end::pt2[]
 
@@ -365,18 +549,145 @@ mod test {
 
 	#[test]
 	fn sample() {
-		let (rules, inputs) = read_input(&read_file!("sample_input.txt")).unwrap();
-		run_tests((&*inputs, &rules), (pt1, 19114), (pt2, 167_409_079_868_000));
+		let (workflows, parts) = read_input(&read_file!("sample_input.txt")).unwrap();
+		run_tests(
+			(&*parts, &workflows),
+			(pt1, 19114),
+			(pt2, 167_409_079_868_000),
+		);
 	}
 
 	#[test]
 	fn test() {
-		let (rules, inputs) = read_input(&read_file!("input.txt")).unwrap();
+		let (workflows, parts) = read_input(&read_file!("input.txt")).unwrap();
 
 		run_tests(
-			(&*inputs, &rules),
+			(&*parts, &workflows),
 			(pt1, 492_702),
 			(pt2, 138_616_621_185_978),
 		);
 	}
+
+	#[test]
+	fn validation() {
+		for (input, expected) in [
+			(
+				"a{x<10:A,R}\nb{A}\n\n{x=1,m=2,a=3,s=4}",
+				r#"no workflow named "in""#,
+			),
+			(
+				"in{x<10:a,R}\na{A}\nb{R}\na{m>5:R,A}",
+				r#"line 4: workflow "a" was already defined on line 2"#,
+			),
+			(
+				"in{x<10:a,m<5:qq,R}\na{s>3:A,zz}",
+				"line 1: workflow \"in\" sends parts to undefined workflow \"qq\"\n\
+				line 2: workflow \"a\" sends parts to undefined workflow \"zz\"",
+			),
+			(
+				"in{x<10:qq,m<5:zz,s>3:qq,qq}\na{qq}",
+				"line 1: workflow \"in\" sends parts to undefined workflow \"qq\"\n\
+				line 1: workflow \"in\" sends parts to undefined workflow \"zz\"\n\
+				line 2: workflow \"a\" sends parts to undefined workflow \"qq\"",
+			),
+			(
+				"in{x<10:a,m=3:R,R}\na{s>3:qq,x>9:A}",
+				"line 1: expected a rule like \"x<5:A\" before the last comma, got \"m=3:R\"\n\
+				line 2: workflow \"a\" must end with a workflow, A or R, got \"x>9:A\"\n\
+				line 2: workflow \"a\" sends parts to undefined workflow \"qq\"",
+			),
+			(
+				"in{x<10:a,R}\na{m<5:b,A}\nb{s>3:in,R}\nc{c}",
+				"workflows form a cycle: in (line 1) -> a (line 2) -> b (line 3) -> in (line 1)\n\
+				workflows form a cycle: c (line 4) -> c (line 4)",
+			),
+		] {
+			let Err(AocError::Other(err)) = read_input(input) else {
+				panic!("{input:?} should not have been valid")
+			};
+			assert_eq!(err, expected);
+		}
+	}
+
+	#[test]
+	fn attributes() {
+		let (mut workflows, parts) = read_input(
+			"in{hp>=50:fast,hp==10:A,R}\n\
+			fast{speed<=3:R,A}\n\
+			\n\
+			{hp=50,speed=3}\n\
+			{speed=9,hp=10}\n\
+			{hp=11,speed=9}\n\
+			{hp=70,speed=4}",
+		)
+		.unwrap();
+		assert_eq!(parts, [[50, 3], [10, 9], [11, 9], [70, 4]]);
+
+		workflows.set_bounds("hp", (1, 100)).unwrap();
+		workflows.set_bounds("speed", (1, 10)).unwrap();
+		// 51 * 7 with 50 <= hp and 4 <= speed, and 1 * 10 with hp == 10
+		run_tests((&*parts, &workflows), (pt1, 93), (pt2, 367));
+
+		for (res, expected) in [
+			(
+				workflows.set_bounds("mp", (1, 100)),
+				r#"no attribute named "mp""#,
+			),
+			(
+				workflows.set_bounds("hp", (5, 4)),
+				r#"attribute "hp" can't range from 5 to 4"#,
+			),
+			(
+				workflows.set_bounds("hp", (1, i64::MAX)),
+				r#"attribute "hp" can't range from 1 to 9223372036854775807"#,
+			),
+			(
+				read_input("in{hp<5:A,R}\n\n{speed=3}").map(|_| ()),
+				r#"line 3: part has no value for attribute "hp""#,
+			),
+			(
+				read_input("in{hp<5:A,R}\n\n{hp=3}\n{hp=3,speed=3}").map(|_| ()),
+				r#"line 3: part has no value for attribute "speed""#,
+			),
+			(
+				read_input("in{hp<5:A,R}\n\n{hp=3}\n{hp=1,hp=2}").map(|_| ()),
+				r#"line 4: part has more than one value for attribute "hp""#,
+			),
+			// only the last item of a workflow can be a bare target
+			(
+				read_input("in{x<5:A,R}\nb{x=>5:A,R}").map(|_| ()),
+				r#"line 2: expected a rule like "x<5:A" before the last comma, got "x=>5:A""#,
+			),
+			(
+				read_input("in{x<99999999999999999999:A,R}").map(|_| ()),
+				r#"line 1: value 99999999999999999999 in rule "x<99999999999999999999:A" doesn't fit in an i64"#,
+			),
+			(
+				read_input("in{x<5:A,m=3:R,A}").map(|_| ()),
+				r#"line 1: expected a rule like "x<5:A" before the last comma, got "m=3:R""#,
+			),
+			(
+				read_input("in{x<5:A,R,A}").map(|_| ()),
+				r#"line 1: expected a rule like "x<5:A" before the last comma, got "R""#,
+			),
+			(
+				read_input("in{x<5:A,x>9:R}").map(|_| ()),
+				r#"line 1: workflow "in" must end with a workflow, A or R, got "x>9:R""#,
+			),
+		] {
+			let Err(AocError::Other(err)) = res else {
+				panic!("expected an error: {expected}")
+			};
+			assert_eq!(err, expected);
+		}
+
+		// conditions that can't hold, right at the edges of `i64`
+		let (workflows, parts) = read_input(
+			"in{x<-9223372036854775808:R,x>9223372036854775807:R,A}\n\
+			\n\
+			{x=-5}",
+		)
+		.unwrap();
+		run_tests((&*parts, &workflows), (pt1, -5), (pt2, 4000));
+	}
 }
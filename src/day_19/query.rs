@@ -0,0 +1,322 @@
+//! Questions a rule author might ask to check that their workflows do what they meant:
+//! which parts get accepted, by which workflows, and why.
+
+use super::{regions, total_volume, Action, Criteria, Outcome, Region, Workflows};
+use crate::{hyperrect::union_volume, AocError, AocResult};
+use std::ops::ControlFlow;
+
+impl Workflows {
+	/// Every attribute, in the order that parts and regions list their values in
+	pub fn attributes(&self) -> Vec<&str> {
+		self.attrs.0.keys().map(String::as_str).collect()
+	}
+
+	/// The disjoint regions of parts that are accepted, each with the workflows that lead
+	/// there
+	pub fn accepted_regions(&self) -> Vec<Region<'_>> {
+		regions(self)
+			.into_iter()
+			.filter(|region| region.outcome == Outcome::Accept)
+			.collect()
+	}
+
+	/// The number of parts that the workflow `name` itself sends to `A`, or `None` if
+	/// that's too many for an `i64`
+	pub fn volume_accepted_by(&self, name: &str) -> Option<i64> {
+		total_volume(
+			self.accepted_regions()
+				.iter()
+				.filter(|region| region.path.last() == Some(&name)),
+		)
+	}
+
+	/// The accepted parts whose values are within `ranges`, one (inclusive) range per
+	/// attribute in the order of [`Workflows::attributes`]: the accepted regions, cut down
+	/// to those ranges
+	/// ## Errors
+	/// If there isn't one range per attribute, or any of them is empty or ends at
+	/// `i64::MAX`
+	pub fn accepted_within(&self, ranges: &[(i64, i64)]) -> AocResult<Vec<Region<'_>>> {
+		if ranges.len() != self.attrs.len() {
+			return Err(AocError::Other(format!(
+				"expected {} ranges, one per attribute, got {}",
+				self.attrs.len(),
+				ranges.len()
+			)));
+		}
+		let bounds = Criteria::new(ranges.to_vec())
+			.ok_or_else(|| AocError::Other(format!("can't look for parts within {ranges:?}")))?;
+
+		Ok(self
+			.accepted_regions()
+			.into_iter()
+			.filter_map(|region| {
+				Some(Region {
+					criteria: region.criteria.intersection(&bounds)?,
+					..region
+				})
+			})
+			.collect())
+	}
+
+	/// The number of parts within the attributes' bounds that end up at `R`, by whichever
+	/// workflow, or `None` if that's too many for an `i64`: whatever's left of the bounds
+	/// once every accepted region is cut out of them
+	pub fn rejected_volume(&self) -> Option<i64> {
+		let mut rejected = vec![self.attrs.criteria()];
+		for region in self.accepted_regions() {
+			rejected = rejected
+				.iter()
+				.flat_map(|rejected| rejected.subtract(&region.criteria))
+				.collect();
+		}
+		union_volume(&rejected)
+	}
+
+	/// Where `part`, with a value for each attribute in the order of
+	/// [`Workflows::attributes`], ends up, and the workflows it passes through on the way,
+	/// starting with `in`
+	/// ## Errors
+	/// If `part` doesn't have one value per attribute
+	pub fn route(&self, part: &[i64]) -> AocResult<(Outcome, Vec<&str>)> {
+		if part.len() != self.attrs.len() {
+			return Err(AocError::Other(format!(
+				"expected {} values, one per attribute, got {}",
+				self.attrs.len(),
+				part.len()
+			)));
+		}
+
+		let mut path = Vec::new();
+		let mut name = "in";
+
+		loop {
+			path.push(name);
+			let rule_set = &self.rules[name];
+
+			let Action(action) = rule_set
+				.rules
+				.iter()
+				.find(|rule| rule.condition.holds_for(part))
+				.map_or(&rule_set.otherwise, |rule| &rule.action);
+
+			match action {
+				ControlFlow::Continue(next) => name = next,
+				&ControlFlow::Break(outcome) => return Ok((outcome, path)),
+			}
+		}
+	}
+}
+
+impl<'a> Region<'a> {
+	/// The (inclusive) range of values of each attribute, in the order of
+	/// [`Workflows::attributes`]
+	pub fn ranges(&self) -> &[(i64, i64)] {
+		self.criteria.ranges()
+	}
+
+	pub fn path(&self) -> &[&'a str] {
+		&self.path
+	}
+
+	pub fn outcome(&self) -> Outcome {
+		self.outcome
+	}
+
+	/// Whether `part`, with a value for each attribute in the order of
+	/// [`Workflows::attributes`], is in the region; never if it has the wrong number of
+	/// values
+	pub fn contains(&self, part: &[i64]) -> bool {
+		self.criteria.contains_point(part)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::super::{compile, pt2, read_input};
+	use super::*;
+	use crate::read_file;
+
+	#[test]
+	fn sample() {
+		use Outcome::*;
+
+		let (workflows, parts) = read_input(&read_file!("sample_input.txt")).unwrap();
+
+		for (part, expected) in parts.iter().zip([
+			(Accept, vec!["in", "qqz", "qs", "lnx"]),
+			(Reject, vec!["in", "px", "rfg", "gd"]),
+			(Accept, vec!["in", "qqz", "hdj", "pv"]),
+			(Reject, vec!["in", "px", "qkq", "crn"]),
+			(Accept, vec!["in", "px", "rfg"]),
+		]) {
+			assert_eq!(workflows.route(part).unwrap(), expected);
+		}
+
+		let accepted = workflows
+			.accepted_regions()
+			.into_iter()
+			.map(|region| region.criteria)
+			.collect::<Vec<_>>();
+		for (i, criteria1) in accepted.iter().enumerate() {
+			for criteria2 in &accepted[i + 1..] {
+				assert_eq!(criteria1.intersection(criteria2), None);
+			}
+		}
+
+		let accepted_volume = pt2((&parts, &workflows));
+		assert_eq!(union_volume(&accepted), Some(accepted_volume));
+		assert_eq!(
+			total_volume(
+				regions(&workflows)
+					.iter()
+					.filter(|region| region.outcome == Reject)
+			),
+			workflows.rejected_volume()
+		);
+		assert_eq!(
+			accepted_volume + workflows.rejected_volume().unwrap(),
+			4000_i64.pow(4)
+		);
+		assert_eq!(
+			workflows
+				.rules
+				.keys()
+				.map(|name| workflows.volume_accepted_by(name).unwrap())
+				.sum::<i64>(),
+			accepted_volume
+		);
+	}
+
+	#[test]
+	fn witnesses() {
+		for input in [read_file!("sample_input.txt"), read_file!("input.txt")] {
+			let (workflows, parts) = read_input(&input).unwrap();
+
+			// every part in a region takes its path, including the corners
+			let accepted = workflows.accepted_regions();
+			for region in &accepted {
+				for corner in [
+					region
+						.ranges()
+						.iter()
+						.map(|&(low, _)| low)
+						.collect::<Vec<_>>(),
+					region.ranges().iter().map(|&(_, high)| high).collect(),
+				] {
+					assert!(region.contains(&corner));
+					assert_eq!(
+						workflows.route(&corner).unwrap(),
+						(region.outcome, region.path.clone())
+					);
+				}
+				assert_eq!(region.criteria.subtract(&workflows.attrs.criteria()), []);
+			}
+
+			assert_eq!(
+				pt2((&parts, &workflows)) + workflows.rejected_volume().unwrap(),
+				4000_i64.pow(4)
+			);
+
+			// an accepted part is in exactly one accepted region, the one it passes through
+			let tree = compile(&workflows).tree;
+			for part in &parts {
+				let (outcome, path) = workflows.route(part).unwrap();
+				assert_eq!(outcome, tree.evaluate(part));
+
+				let containing = accepted
+					.iter()
+					.filter(|region| region.contains(part))
+					.collect::<Vec<_>>();
+				match outcome {
+					Outcome::Accept => {
+						assert_eq!(containing.len(), 1);
+						assert_eq!(containing[0].path, path);
+					}
+					Outcome::Reject => assert!(containing.is_empty()),
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn by_workflow() {
+		let (workflows, _) =
+			read_input("in{hp>=50:fast,hp==10:A,R}\nfast{speed<=3:R,A}\nslow{A}").unwrap();
+
+		assert_eq!(workflows.attributes(), ["hp", "speed"]);
+		assert_eq!(
+			workflows
+				.accepted_regions()
+				.iter()
+				.map(|region| (region.ranges(), region.path(), region.outcome()))
+				.collect::<Vec<_>>(),
+			[
+				(&[(10, 10), (1, 4000)][..], &["in"][..], Outcome::Accept),
+				(&[(50, 4000), (4, 4000)], &["in", "fast"], Outcome::Accept),
+			]
+		);
+		assert_eq!(workflows.volume_accepted_by("in"), Some(4000));
+		assert_eq!(workflows.volume_accepted_by("fast"), Some(3951 * 3997));
+		assert_eq!(workflows.volume_accepted_by("slow"), Some(0));
+		assert_eq!(
+			workflows.rejected_volume(),
+			Some(4000 * 4000 - 4000 - 3951 * 3997)
+		);
+		assert_eq!(
+			workflows.route(&[60, 2]).unwrap(),
+			(Outcome::Reject, vec!["in", "fast"])
+		);
+
+		assert_eq!(
+			workflows
+				.accepted_within(&[(1, 60), (3, 5)])
+				.unwrap()
+				.iter()
+				.map(|region| (region.ranges(), region.path()))
+				.collect::<Vec<_>>(),
+			[
+				(&[(10, 10), (3, 5)][..], &["in"][..]),
+				(&[(50, 60), (4, 5)], &["in", "fast"]),
+			]
+		);
+		assert!(workflows
+			.accepted_within(&[(1, 9), (1, 4000)])
+			.unwrap()
+			.is_empty());
+		for (ranges, expected) in [
+			(
+				&[(1, 60)][..],
+				"expected 2 ranges, one per attribute, got 1",
+			),
+			(
+				&[(1, 60), (5, 3)],
+				"can't look for parts within [(1, 60), (5, 3)]",
+			),
+		] {
+			let Err(AocError::Other(err)) = workflows.accepted_within(ranges) else {
+				panic!("{ranges:?} should have been rejected")
+			};
+			assert_eq!(err, expected);
+		}
+		for part in [&[60][..], &[60, 2, 0]] {
+			let Err(AocError::Other(err)) = workflows.route(part) else {
+				panic!("{part:?} should have been rejected")
+			};
+			assert_eq!(
+				err,
+				format!("expected 2 values, one per attribute, got {}", part.len())
+			);
+		}
+		let fast = &workflows.accepted_regions()[1];
+		assert!(fast.contains(&[50, 4]) && !fast.contains(&[49, 4]));
+		assert!(!fast.contains(&[50]) && !fast.contains(&[50, 4, 0]));
+
+		let mut workflows = workflows;
+		workflows
+			.set_bounds("hp", (i64::MIN, i64::MAX - 1))
+			.unwrap();
+		assert_eq!(workflows.volume_accepted_by("in"), Some(4000));
+		assert_eq!(workflows.volume_accepted_by("fast"), None);
+	}
+}
@@ -0,0 +1,276 @@
+//! All the workflows inlined into one decision tree, starting from `in`. Each workflow is
+//! inlined separately for every way into it, knowing which parts can take that way, so
+//! any rule that no such part can trigger (or that every such part triggers) is dropped.
+//!
+//! The ways into a workflow are taken by disjoint sets of parts, so however many paths
+//! there are through the workflows, the tree has at most one leaf per box that the
+//! attributes' bounds are cut into at every condition's threshold.
+
+use super::{Action, Attrs, Comparison, Condition, Criteria, Outcome, Rule, RuleSet, Workflows};
+use indexmap::IndexMap;
+use std::{collections::HashSet, fmt::Write, ops::ControlFlow};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) enum Tree {
+	Leaf(Outcome),
+	Branch {
+		condition: Condition,
+		yes: Box<Tree>,
+		no: Box<Tree>,
+	},
+}
+
+#[derive(Debug)]
+pub(super) struct Compiled<'a> {
+	pub(super) tree: Tree,
+	/// The workflows that no part ever reaches, in the order they were defined
+	pub(super) dead: Vec<&'a str>,
+}
+
+struct Compiler<'a> {
+	rules: &'a IndexMap<String, RuleSet>,
+	reached: HashSet<&'a str>,
+}
+
+impl<'a> Compiler<'a> {
+	fn workflow(&mut self, name: &'a str, criteria: Criteria) -> Tree {
+		let RuleSet {
+			rules, otherwise, ..
+		} = &self.rules[name];
+
+		self.reached.insert(name);
+		self.rules(rules, otherwise, criteria)
+	}
+
+	/// The tree for the rules of a workflow from the first of `rules` on
+	fn rules(&mut self, rules: &'a [Rule], otherwise: &'a Action, criteria: Criteria) -> Tree {
+		let Some((Rule { condition, action }, rest)) = rules.split_first() else {
+			return self.action(otherwise, criteria);
+		};
+
+		let (holds, fails) = condition.split(criteria);
+		let yes = holds.map(|holds| self.action(action, holds));
+		let mut fails = fails
+			.into_iter()
+			.map(|fails| self.rules(rest, otherwise, fails));
+		let no = match (fails.next(), fails.next()) {
+			// only `==` leaves parts on both sides
+			(Some(below), Some(above)) => Some(branch(
+				Condition {
+					cmp: Comparison::Lt,
+					..*condition
+				},
+				below,
+				above,
+			)),
+			(no, _) => no,
+		};
+
+		match (yes, no) {
+			(Some(yes), Some(no)) => branch(*condition, yes, no),
+			(Some(tree), None) | (None, Some(tree)) => tree,
+			(None, None) => unreachable!("criteria are never empty"),
+		}
+	}
+
+	fn action(&mut self, Action(action): &'a Action, criteria: Criteria) -> Tree {
+		match action {
+			ControlFlow::Continue(name) => self.workflow(name, criteria),
+			&ControlFlow::Break(outcome) => Tree::Leaf(outcome),
+		}
+	}
+}
+
+/// A branch on `condition`, or just one side if both sides are the same
+fn branch(condition: Condition, yes: Tree, no: Tree) -> Tree {
+	if yes == no {
+		yes
+	} else {
+		Tree::Branch {
+			condition,
+			yes: Box::new(yes),
+			no: Box::new(no),
+		}
+	}
+}
+
+/// Inlines every workflow reachable from `in` into one tree, for parts whose attributes
+/// are all within their bounds. The workflows must have been validated, so that
+/// inlining them terminates.
+pub(super) fn compile(Workflows { rules, attrs }: &Workflows) -> Compiled<'_> {
+	let mut compiler = Compiler {
+		rules,
+		reached: HashSet::new(),
+	};
+	let tree = compiler.workflow("in", attrs.criteria());
+	let dead = rules
+		.keys()
+		.map(String::as_str)
+		.filter(|name| !compiler.reached.contains(name))
+		.collect();
+
+	Compiled { tree, dead }
+}
+
+impl Outcome {
+	fn to_char(self) -> char {
+		match self {
+			Outcome::Accept => 'A',
+			Outcome::Reject => 'R',
+		}
+	}
+}
+
+impl Tree {
+	pub(super) fn evaluate(&self, part: &[i64]) -> Outcome {
+		let mut tree = self;
+		loop {
+			match tree {
+				&Tree::Leaf(outcome) => return outcome,
+				Tree::Branch { condition, yes, no } => {
+					tree = if condition.holds_for(part) { yes } else { no };
+				}
+			}
+		}
+	}
+
+	/// The tree written back out as workflows, one per line, starting with `in`. Each
+	/// workflow follows a chain of `no` branches, with a new workflow for each `yes`
+	/// branch that isn't a leaf.
+	pub(super) fn to_workflows(&self, attrs: &Attrs) -> String {
+		let mut workflows = String::new();
+		let mut to_write = vec![("in".to_owned(), self)];
+		let mut n_named = 0;
+
+		while let Some((name, mut tree)) = to_write.pop() {
+			let mut rules = Vec::new();
+			while let Tree::Branch { condition, yes, no } = tree {
+				let target = match &**yes {
+					&Tree::Leaf(outcome) => outcome.to_char().to_string(),
+					yes @ Tree::Branch { .. } => {
+						n_named += 1;
+						let target = format!("w{n_named}");
+						to_write.push((target.clone(), yes));
+						target
+					}
+				};
+				let Condition { attr, cmp, value } = condition;
+				rules.push(format!("{}{cmp}{value}:{target}", attrs.name(*attr)));
+				tree = no;
+			}
+			let Tree::Leaf(otherwise) = tree else {
+				unreachable!("the chain ends at a leaf")
+			};
+			rules.push(otherwise.to_char().to_string());
+
+			writeln!(workflows, "{name}{{{}}}", rules.join(",")).unwrap();
+		}
+
+		workflows
+	}
+}
+
+impl Workflows {
+	/// The workflows with every workflow inlined into `in`, and then split back up into
+	/// workflows wherever a rule sends parts somewhere other than `A` or `R`, written out
+	/// one per line in the same syntax as the input. Parts end up in the same place as
+	/// they would have with the original workflows.
+	pub fn simplified(&self) -> String {
+		compile(self).tree.to_workflows(&self.attrs)
+	}
+
+	/// The workflows that no part ever reaches, in the order they were defined
+	pub fn dead_workflows(&self) -> Vec<&str> {
+		compile(self).dead
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::super::{pt1, pt2, read_input};
+	use super::*;
+	use crate::{read_file, AocError};
+
+	fn simplified(input: &str) -> String {
+		input.parse::<Workflows>().unwrap().simplified()
+	}
+
+	#[test]
+	fn simplify() {
+		let workflows = "\
+			in{x<10:a,m>5:c,R}\n\
+			a{x>20:b,s<3:R,A}\n\
+			b{A}\n\
+			c{a<100:A,A}\n\
+			d{R}"
+			.parse::<Workflows>()
+			.unwrap();
+		assert_eq!(workflows.simplified(), "in{x<10:w1,m>5:A,R}\nw1{s<3:R,A}\n");
+		assert_eq!(workflows.dead_workflows(), ["b", "d"]);
+
+		assert_eq!(
+			simplified("in{x<10:A,m>5:R,m<3:A,x>3000:R,A}"),
+			"in{x<10:A,m>5:R,m<3:A,x>3000:R,A}\n"
+		);
+		assert_eq!(simplified("in{x<10:A,m>5:a,A}\na{x>5:A,A}"), "in{A}\n");
+		assert_eq!(simplified("in{x==10:R,x<=4000:A,R}"), "in{x==10:R,A}\n");
+		assert_eq!(
+			simplified("in{x==10:R,x<5:A,R}"),
+			"in{x==10:R,x<10:w1,R}\nw1{x<5:A,R}\n"
+		);
+		assert_eq!(simplified("in{x>=1:A,R}"), "in{A}\n");
+
+		// 2^40 paths through the workflows, but only two boxes of parts
+		let mut chain = "in{x<5:w1,w1}\n".to_owned();
+		for i in 1..40 {
+			writeln!(chain, "w{i}{{x<5:w{0},w{0}}}", i + 1).unwrap();
+		}
+		chain.push_str("w40{x<5:A,R}");
+		assert_eq!(simplified(&chain), "in{x<5:A,R}\n");
+
+		// inlining these would never finish, or would run into a workflow that isn't there,
+		// so they're turned away before they get here
+		for (input, expected) in [
+			(
+				"in{x<10:a,A}\na{m<10:in,R}",
+				"workflows form a cycle: in (line 1) -> a (line 2) -> in (line 1)",
+			),
+			(
+				"in{x<10:a,A}\na{m<10:b,R}",
+				r#"line 2: workflow "a" sends parts to undefined workflow "b""#,
+			),
+		] {
+			let Err(AocError::Other(err)) = input.parse::<Workflows>() else {
+				panic!("{input:?} should not compile")
+			};
+			assert_eq!(err, expected);
+		}
+	}
+
+	#[test]
+	fn round_trip() {
+		for input in [
+			read_file!("sample_input.txt"),
+			read_file!("input.txt"),
+			"in{hp>=50:fast,hp==10:A,R}\nfast{speed<=3:R,speed==7:R,A}\n\n{hp=1,speed=2}"
+				.to_owned(),
+		] {
+			let (workflows, parts) = read_input(&input).unwrap();
+			let (_, parts_str) = input.split_once("\n\n").unwrap();
+
+			// the simplified workflows may mention the attributes in a different order
+			let (simplified, simplified_parts) =
+				read_input(&format!("{}\n{parts_str}", workflows.simplified())).unwrap();
+			assert!(simplified.rules.len() <= workflows.rules.len());
+			assert!(simplified.dead_workflows().is_empty());
+			assert_eq!(
+				pt1((&simplified_parts, &simplified)),
+				pt1((&parts, &workflows))
+			);
+			assert_eq!(
+				pt2((&simplified_parts, &simplified)),
+				pt2((&parts, &workflows))
+			);
+		}
+	}
+}
@@ -1,9 +1,9 @@
 use std::{char::TryFromCharError, num::ParseIntError};
 
-pub(crate) type AocResult<T> = Result<T, AocError>;
+pub type AocResult<T> = Result<T, AocError>;
 
 #[derive(Debug)]
-pub(crate) enum AocError {
+pub enum AocError {
 	FromIntError(ParseIntError),
 	StrumParse(strum::ParseError),
 	OptionWasNone(&'static str),
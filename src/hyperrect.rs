@@ -0,0 +1,297 @@
+//! Axis-aligned boxes of lattice points in any number of dimensions, each the product of
+//! an inclusive range of integers along every axis. Ranges must end below `i64::MAX`, so
+//! that every box has an exclusive end.
+
+/// Never empty: every range has `low <= high < i64::MAX`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct HyperRect {
+	ranges: Vec<(i64, i64)>,
+}
+
+/// Whether a box can have the range `(low, high)` along an axis
+fn is_valid((low, high): (i64, i64)) -> bool {
+	low <= high && high < i64::MAX
+}
+
+impl HyperRect {
+	/// `None` if any of the ranges is empty or ends at `i64::MAX`
+	pub(crate) fn new(ranges: Vec<(i64, i64)>) -> Option<Self> {
+		ranges
+			.iter()
+			.all(|&range| is_valid(range))
+			.then_some(Self { ranges })
+	}
+
+	pub(crate) fn ranges(&self) -> &[(i64, i64)] {
+		&self.ranges
+	}
+
+	/// The number of points in the box, or `None` if that's too many for an `i64`
+	pub(crate) fn volume(&self) -> Option<i64> {
+		self.ranges.iter().try_fold(1_i64, |volume, &(low, high)| {
+			volume.checked_mul((high + 1).checked_sub(low)?)
+		})
+	}
+
+	/// The box with its range along `axis` replaced by `range`, or `None` if that's empty
+	/// or ends at `i64::MAX`
+	pub(crate) fn with_range(&self, axis: usize, range: (i64, i64)) -> Option<Self> {
+		is_valid(range).then(|| {
+			let mut rect = self.clone();
+			rect.ranges[axis] = range;
+			rect
+		})
+	}
+
+	/// The points whose coordinate along `axis` is within `(low, high)`, if there are any,
+	/// and the rest, as up to two disjoint boxes: those below the range and those above
+	/// it
+	pub(crate) fn split(&self, axis: usize, (low, high): (i64, i64)) -> (Option<Self>, Vec<Self>) {
+		let (self_low, self_high) = self.ranges[axis];
+
+		let inside = self.with_range(axis, (self_low.max(low), self_high.min(high)));
+		let outside = [
+			low.checked_sub(1)
+				.and_then(|below| self.with_range(axis, (self_low, self_high.min(below)))),
+			high.checked_add(1)
+				.and_then(|above| self.with_range(axis, (self_low.max(above), self_high))),
+		]
+		.into_iter()
+		.flatten()
+		.collect();
+
+		(inside, outside)
+	}
+
+	/// The points in both boxes, if there are any
+	pub(crate) fn intersection(&self, other: &Self) -> Option<Self> {
+		Self::new(
+			self.ranges
+				.iter()
+				.zip(&other.ranges)
+				.map(|(&(low1, high1), &(low2, high2))| (low1.max(low2), high1.min(high2)))
+				.collect(),
+		)
+	}
+
+	/// The points in `self` but not `other`, as disjoint boxes: at most two per axis, as
+	/// `self` is cut down to `other`'s range along each axis in turn
+	pub(crate) fn subtract(&self, other: &Self) -> Vec<Self> {
+		let disjoint = self
+			.ranges
+			.iter()
+			.zip(&other.ranges)
+			.any(|(&(low1, high1), &(low2, high2))| high1 < low2 || high2 < low1);
+		if disjoint {
+			return vec![self.clone()];
+		}
+
+		let mut pieces = Vec::new();
+		let mut rest = self.clone();
+
+		for (axis, &range) in other.ranges.iter().enumerate() {
+			let (inside, outside) = rest.split(axis, range);
+			pieces.extend(outside);
+			match inside {
+				Some(inside) => rest = inside,
+				None => unreachable!("the boxes overlap along every axis"),
+			}
+		}
+
+		pieces
+	}
+
+	/// Whether `point` is in the box; never if it has the wrong number of coordinates
+	pub(crate) fn contains_point(&self, point: &[i64]) -> bool {
+		point.len() == self.ranges.len()
+			&& self
+				.ranges
+				.iter()
+				.zip(point)
+				.all(|(&(low, high), x)| (low..=high).contains(x))
+	}
+}
+
+/// The number of points in at least one of `rects`, which must all have the same
+/// dimension. The first axis is cut at every box's ends into slabs, each covered by the
+/// same boxes throughout; the volume of each slab is its width times the union volume,
+/// over the remaining axes, of the boxes covering it. `None` if the volume is too big for
+/// an `i64`.
+pub(crate) fn union_volume(rects: &[HyperRect]) -> Option<i64> {
+	fn slabs(rects: &[&[(i64, i64)]]) -> Option<i64> {
+		let Some(first) = rects.first() else {
+			return Some(0);
+		};
+		if first.is_empty() {
+			return Some(1);
+		}
+
+		let mut cuts = rects
+			.iter()
+			.flat_map(|ranges| [ranges[0].0, ranges[0].1 + 1])
+			.collect::<Vec<_>>();
+		cuts.sort_unstable();
+		cuts.dedup();
+
+		cuts.windows(2).try_fold(0_i64, |volume, window| {
+			let &[start, end] = window else {
+				unreachable!()
+			};
+			let covering = rects
+				.iter()
+				.filter(|ranges| ranges[0].0 <= start && end <= ranges[0].1 + 1)
+				.map(|ranges| &ranges[1..])
+				.collect::<Vec<_>>();
+			volume.checked_add(end.checked_sub(start)?.checked_mul(slabs(&covering)?)?)
+		})
+	}
+
+	slabs(&rects.iter().map(HyperRect::ranges).collect::<Vec<_>>())
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn cuboid(ranges: [(i64, i64); 3]) -> HyperRect {
+		HyperRect::new(ranges.to_vec()).unwrap()
+	}
+
+	/// Every point in any of `rects`
+	fn points(rects: &[HyperRect]) -> Vec<[i64; 3]> {
+		let mut points = rects
+			.iter()
+			.flat_map(|rect| {
+				let &[(x0, x1), (y0, y1), (z0, z1)] = rect.ranges() else {
+					panic!("not a cuboid")
+				};
+				(x0..=x1).flat_map(move |x| {
+					(y0..=y1).flat_map(move |y| (z0..=z1).map(move |z| [x, y, z]))
+				})
+			})
+			.collect::<Vec<_>>();
+		points.sort_unstable();
+		points.dedup();
+		points
+	}
+
+	#[test]
+	fn overlapping_cuboids() {
+		let cube = cuboid([(0, 2), (0, 2), (0, 2)]);
+		let shifted = cuboid([(1, 3), (1, 3), (1, 3)]);
+		let inside = cuboid([(1, 1), (0, 2), (2, 2)]);
+
+		assert_eq!(cube.volume(), Some(27));
+		assert_eq!(
+			cube.intersection(&shifted),
+			Some(cuboid([(1, 2), (1, 2), (1, 2)]))
+		);
+		assert_eq!(
+			union_volume(&[cube.clone(), shifted.clone()]),
+			Some(27 + 27 - 8)
+		);
+		assert_eq!(
+			union_volume(&[cube.clone(), shifted.clone(), inside]),
+			Some(46)
+		);
+		assert_eq!(union_volume(&[]), Some(0));
+
+		// cubes next to `cube` across a face, an edge and a corner
+		let face = cuboid([(3, 5), (0, 2), (0, 2)]);
+		let edge = cuboid([(3, 5), (3, 5), (0, 2)]);
+		let corner = cuboid([(3, 5), (3, 5), (3, 5)]);
+		for other in [&face, &edge, &corner] {
+			assert_eq!(cube.intersection(other), None);
+			assert_eq!(union_volume(&[cube.clone(), other.clone()]), Some(54));
+		}
+		assert_eq!(
+			cube.intersection(&cuboid([(2, 4), (2, 4), (2, 4)]))
+				.unwrap()
+				.volume(),
+			Some(1)
+		);
+	}
+
+	#[test]
+	fn containment() {
+		let a = cuboid([(0, 2), (0, 2), (0, 2)]);
+
+		// full
+		let inside = cuboid([(1, 1), (0, 2), (2, 2)]);
+		assert_eq!(inside.subtract(&a), []);
+		assert_eq!(a.intersection(&inside), Some(inside.clone()));
+		assert!(a.contains_point(&[0, 2, 1]));
+		assert!(!a.contains_point(&[0, 2]) && !a.contains_point(&[0, 2, 1, 0]));
+
+		// partial
+		let b = cuboid([(1, 3), (1, 3), (1, 3)]);
+		assert!(a.contains_point(&[1, 1, 1]) && b.contains_point(&[1, 1, 1]));
+		assert!(!b.contains_point(&[0, 2, 1]));
+
+		// none
+		let c = cuboid([(5, 6), (0, 2), (0, 2)]);
+		assert_eq!(a.subtract(&c), std::slice::from_ref(&a));
+	}
+
+	#[test]
+	fn subtract() {
+		let boxes = [
+			cuboid([(0, 3), (0, 3), (0, 3)]),
+			cuboid([(1, 2), (1, 2), (1, 2)]),
+			cuboid([(2, 5), (-1, 1), (0, 0)]),
+			cuboid([(0, 0), (0, 0), (0, 0)]),
+			cuboid([(-2, 1), (2, 4), (1, 5)]),
+			cuboid([(4, 5), (4, 5), (4, 5)]),
+			cuboid([(1, 1), (-3, 6), (2, 2)]),
+		];
+
+		for a in &boxes {
+			for b in &boxes {
+				let pieces = a.subtract(b);
+				for (i, piece1) in pieces.iter().enumerate() {
+					assert_eq!(piece1.intersection(a).as_ref(), Some(piece1));
+					assert_eq!(piece1.intersection(b), None);
+					for piece2 in &pieces[i + 1..] {
+						assert_eq!(piece1.intersection(piece2), None);
+					}
+				}
+
+				let expected = points(std::slice::from_ref(a))
+					.into_iter()
+					.filter(|point| !b.contains_point(point))
+					.collect::<Vec<_>>();
+				assert_eq!(points(&pieces), expected);
+				assert_eq!(
+					pieces
+						.iter()
+						.map(|piece| piece.volume().unwrap())
+						.sum::<i64>(),
+					expected.len().try_into().unwrap()
+				);
+			}
+		}
+	}
+
+	#[test]
+	fn limits() {
+		assert_eq!(HyperRect::new(vec![(0, i64::MAX)]), None);
+		assert_eq!(HyperRect::new(vec![(1, 0)]), None);
+		assert_eq!(HyperRect::new(vec![]).unwrap().volume(), Some(1));
+
+		let rect = HyperRect::new(vec![(0, 9), (0, 9)]).unwrap();
+		assert_eq!(rect.with_range(1, (5, i64::MAX)), None);
+		assert_eq!(rect.with_range(1, (5, 4)), None);
+		assert_eq!(rect.with_range(1, (5, 5)).unwrap().volume(), Some(10));
+
+		// the ranges themselves fit in an `i64`, but not their volumes
+		let widest = HyperRect::new(vec![(i64::MIN, i64::MAX - 1)]).unwrap();
+		assert_eq!(widest.volume(), None);
+		assert_eq!(union_volume(&[widest]), None);
+		let square = HyperRect::new(vec![(0, 1 << 32), (0, 1 << 32)]).unwrap();
+		assert_eq!(square.volume(), None);
+		assert_eq!(union_volume(&[square]), None);
+		let line = HyperRect::new(vec![(i64::MIN, -2)]).unwrap();
+		assert_eq!(line.volume(), Some(i64::MAX));
+		assert_eq!(union_volume(&[line]), Some(i64::MAX));
+	}
+}
@@ -9,6 +9,7 @@
 	iter_next_chunk,
 	never_type
 )]
+#![cfg_attr(test, feature(test))]
 #![warn(clippy::pedantic)]
 #![allow(
 	clippy::enum_glob_use,
@@ -18,13 +19,20 @@
 	clippy::similar_names
 )]
 
+#[cfg(test)]
+extern crate test;
+
+pub(crate) mod cycle;
 pub(crate) mod enum_map;
 pub(crate) mod error;
 pub(crate) mod grid;
+pub(crate) mod hyperrect;
+pub(crate) mod polygon;
 pub(crate) mod utils;
 
 pub(crate) use enum_map::EnumMap;
-pub(crate) use error::{AocError, AocResult, ToResultDefaultErr};
+pub(crate) use error::ToResultDefaultErr;
+pub use error::{AocError, AocResult};
 use std::fmt::{Debug, Display};
 
 // tag::mods[]
@@ -117,6 +125,29 @@ pub(crate) fn run_tests<Input, AnsFunc1, AnsFunc2, Actual1, Actual2, Expected1,
 	run_test(input, test_case_2);
 }
 
+/// A xorshift generator (<https://en.wikipedia.org/wiki/Xorshift>), for tests that want
+/// random-looking inputs that are the same every run
+#[cfg(test)]
+pub(crate) struct XorShift(u64);
+
+#[cfg(test)]
+impl XorShift {
+	/// `seed` must be nonzero, or every number will be zero
+	pub(crate) fn new(seed: u64) -> Self {
+		assert_ne!(seed, 0);
+		Self(seed)
+	}
+
+	/// A number in `0..n`, close enough to uniform for small `n`
+	pub(crate) fn below(&mut self, n: u64) -> u64 {
+		let Self(state) = self;
+		*state ^= *state << 13;
+		*state ^= *state >> 7;
+		*state ^= *state << 17;
+		*state % n
+	}
+}
+
 #[macro_export]
 macro_rules! regex {
 	($re:expr $(,)?) => {{
@@ -0,0 +1,208 @@
+//! Polygons whose vertices are lattice points, and the lattice points on and inside them.
+//! All the arithmetic is done in `i128`, so that products of `i64` coordinates can't
+//! overflow.
+
+use num::Integer;
+
+pub(crate) type Point = [i64; 2];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Orientation {
+	/// Counterclockwise when the first axis points right and the second points up, which
+	/// is still counterclockwise when the first axis points down and the second points
+	/// right, as with `[row, col]`, since that's just a quarter turn
+	Positive,
+	Negative,
+	/// Zero area
+	Degenerate,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Location {
+	Inside,
+	Boundary,
+	Outside,
+}
+
+/// A closed polygon, with an edge from each vertex to the next and from the last back to
+/// the first. The edges needn't be axis-aligned. Counts of lattice points assume the
+/// polygon is simple (its edges only meet at shared vertices).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Polygon {
+	vertices: Vec<Point>,
+}
+
+fn widen([x, y]: Point) -> [i128; 2] {
+	[x.into(), y.into()]
+}
+
+impl Polygon {
+	pub(crate) fn new(vertices: Vec<Point>) -> Self {
+		Self { vertices }
+	}
+
+	pub(crate) fn vertices(&self) -> &[Point] {
+		&self.vertices
+	}
+
+	/// Each edge, as its start and end
+	pub(crate) fn edges(&self) -> impl Iterator<Item = (Point, Point)> + '_ {
+		self.vertices
+			.iter()
+			.copied()
+			.zip(self.vertices.iter().copied().cycle().skip(1))
+	}
+
+	/// Twice the signed area, from the shoelace formula
+	/// (<https://en.wikipedia.org/wiki/Shoelace_formula>), with the sign given by
+	/// [`Polygon::orientation`]. Each term fits in an `i128`, but enough of them can
+	/// overflow the sum, in which case this is `None`.
+	pub(crate) fn twice_signed_area(&self) -> Option<i128> {
+		self.edges().try_fold(0_i128, |area, (p1, p2)| {
+			let ([x1, y1], [x2, y2]) = (widen(p1), widen(p2));
+			area.checked_add(x1 * y2 - x2 * y1)
+		})
+	}
+
+	/// Which way the vertices go around, or `None` if the area overflows
+	pub(crate) fn orientation(&self) -> Option<Orientation> {
+		Some(match self.twice_signed_area()?.signum() {
+			1 => Orientation::Positive,
+			-1 => Orientation::Negative,
+			_ => Orientation::Degenerate,
+		})
+	}
+
+	/// The number of lattice points on the edges. An edge from `p1` to `p2` passes
+	/// through `gcd(|dx|, |dy|) + 1` of them, counting both ends; each vertex is the end
+	/// of two edges, so we leave out one end of each.
+	pub(crate) fn n_boundary_points(&self) -> i128 {
+		self.edges()
+			.map(|(p1, p2)| {
+				let ([x1, y1], [x2, y2]) = (widen(p1), widen(p2));
+				(x2 - x1).gcd(&(y2 - y1))
+			})
+			.sum()
+	}
+
+	/// The number of lattice points strictly inside, from Pick's theorem
+	/// (<https://en.wikipedia.org/wiki/Pick%27s_theorem>): `A = i + b/2 - 1`, or `None` if
+	/// the area overflows. A degenerate polygon has nothing inside it, although Pick's
+	/// theorem, which only holds for polygons with some area, would say otherwise.
+	pub(crate) fn n_interior_points(&self) -> Option<i128> {
+		if self.orientation()? == Orientation::Degenerate {
+			return Some(0);
+		}
+		Some((self.twice_signed_area()?.abs() - self.n_boundary_points()) / 2 + 1)
+	}
+
+	/// Where `point` is relative to the polygon, found by counting the edges crossed by a
+	/// ray from `point` in the direction of increasing first coordinate. Works for any
+	/// polygon, not just simple ones, with the even-odd rule deciding what's inside.
+	#[allow(dead_code)]
+	pub(crate) fn locate(&self, point: Point) -> Location {
+		let [px, py] = widen(point);
+		let mut inside = false;
+
+		for (p1, p2) in self.edges() {
+			let ([x1, y1], [x2, y2]) = (widen(p1), widen(p2));
+
+			let cross = (x2 - x1) * (py - y1) - (px - x1) * (y2 - y1);
+			if cross == 0
+				&& x1.min(x2) <= px
+				&& px <= x1.max(x2)
+				&& y1.min(y2) <= py
+				&& py <= y1.max(y2)
+			{
+				return Location::Boundary;
+			}
+
+			// each edge covers the half-open range of second coordinates between its ends,
+			// so a ray through a vertex is counted once, by exactly one of its edges
+			if (y1 > py) != (y2 > py) {
+				// the edge crosses the ray iff it meets the line through `point` at a
+				// greater first coordinate than `point`'s
+				let crosses = if y2 > y1 { cross > 0 } else { cross < 0 };
+				inside ^= crosses;
+			}
+		}
+
+		if inside {
+			Location::Inside
+		} else {
+			Location::Outside
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn diagonal_edges() {
+		// the triangle (0, 0), (4, 0), (0, 6) has gcd(4, 0) + gcd(4, 6) + gcd(0, 6) = 12
+		// boundary points, and area 12, so 12 - 12/2 + 1 = 7 interior points
+		let triangle = Polygon::new(vec![[0, 0], [4, 0], [0, 6]]);
+		assert_eq!(triangle.twice_signed_area(), Some(24));
+		assert_eq!(triangle.orientation(), Some(Orientation::Positive));
+		assert_eq!(triangle.n_boundary_points(), 12);
+		assert_eq!(triangle.n_interior_points(), Some(7));
+		assert_eq!(triangle.locate([2, 3]), Location::Boundary);
+		assert_eq!(triangle.locate([1, 3]), Location::Inside);
+		assert_eq!(triangle.locate([3, 3]), Location::Outside);
+
+		let mut reversed = triangle.vertices().to_vec();
+		reversed.reverse();
+		let reversed = Polygon::new(reversed);
+		assert_eq!(reversed.twice_signed_area(), Some(-24));
+		assert_eq!(reversed.orientation(), Some(Orientation::Negative));
+		assert_eq!(reversed.n_interior_points(), Some(7));
+	}
+
+	#[test]
+	fn orientation() {
+		use Orientation::*;
+
+		// with `[row, col]` vertices, going down the left column first is counterclockwise
+		// on screen, which is positive
+		let square = [[0, 0], [3, 0], [3, 3], [0, 3]];
+		assert_eq!(Polygon::new(square.to_vec()).orientation(), Some(Positive));
+		let mut reversed = square;
+		reversed.reverse();
+		assert_eq!(
+			Polygon::new(reversed.to_vec()).orientation(),
+			Some(Negative)
+		);
+
+		// there and back again along a line encloses nothing
+		let segment = Polygon::new(vec![[0, 0], [2, 2], [4, 4], [2, 2]]);
+		assert_eq!(segment.orientation(), Some(Degenerate));
+		assert_eq!(segment.n_boundary_points(), 8);
+		assert_eq!(segment.n_interior_points(), Some(0));
+		assert_eq!(Polygon::new(vec![]).orientation(), Some(Degenerate));
+	}
+
+	#[test]
+	fn no_overflow() {
+		// coordinates this large overflow `i64` products
+		let huge = Polygon::new(vec![
+			[0, 0],
+			[i64::MAX, 0],
+			[i64::MAX, i64::MAX],
+			[0, i64::MAX],
+		]);
+		assert_eq!(
+			huge.twice_signed_area(),
+			Some(2 * i128::from(i64::MAX).pow(2))
+		);
+		assert_eq!(huge.n_boundary_points(), 4 * i128::from(i64::MAX));
+
+		// but going around it twice overflows the `i128` sum
+		let twice_around = Polygon::new(huge.vertices().repeat(2));
+		assert_eq!(twice_around.twice_signed_area(), None);
+		assert_eq!(twice_around.orientation(), None);
+		assert_eq!(twice_around.n_interior_points(), None);
+	}
+}